@@ -0,0 +1,22 @@
+use esri_ascii_grid::{
+    header::{CornerType, EsriASCIIRasterHeader},
+    writer::EsriASCIIWriter,
+};
+
+/// Returns `test_data/test.asc`, or if it is not available, a grid of awkward floating point values of the same size,
+/// 2000 columns by 1000 rows.
+pub fn grid() -> Vec<u8> {
+    if let Ok(data) = std::fs::read("test_data/test.asc") {
+        return data;
+    }
+    let header: EsriASCIIRasterHeader<f64, f64> =
+        EsriASCIIRasterHeader::new(2000, 1000, 0.0, 0.0, CornerType::Corner, 1.0, Some(-9999.0));
+    let rows = (0..1000).map(|row| {
+        (0..2000)
+            .map(|col| f64::from(row * 2000 + col).sqrt() * 1.37)
+            .collect::<Vec<_>>()
+    });
+    EsriASCIIWriter::new(Vec::new(), header)
+        .write_grid(rows)
+        .unwrap()
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use std::{hint::black_box, io::Cursor};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use esri_ascii_grid::ascii_file::EsriASCIIReader;
+
+fn load(c: &mut Criterion) {
+    let data = common::grid();
+    let open = || -> EsriASCIIReader<_, f64, f64> {
+        EsriASCIIReader::from_file(Cursor::new(data.as_slice())).unwrap()
+    };
+    let mut group = c.benchmark_group("load");
+    group.sample_size(20);
+    group.bench_function("iterator", |b| {
+        b.iter(|| {
+            open()
+                .into_iter()
+                .map(|cell| cell.unwrap().2)
+                .collect::<Vec<_>>()
+        });
+    });
+    group.bench_function("load_all", |b| {
+        b.iter(|| black_box(open().load_all().unwrap()))
+    });
+    group.bench_function("load_all_parallel", |b| {
+        b.iter(|| black_box(open().load_all_parallel().unwrap()));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+//! Compare runs with and without the `fast-parse` feature:
+//!
+//! ```text
+//! cargo bench --bench parse -- --save-baseline from-str
+//! cargo bench --bench parse --features fast-parse -- --baseline from-str
+//! ```
+mod common;
+
+use std::{hint::black_box, io::Cursor};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use esri_ascii_grid::{ascii_file::EsriASCIIReader, cache::CachePolicy};
+
+fn parse(c: &mut Criterion) {
+    let data = common::grid();
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(20);
+    group.bench_function("iterate f64", |b| {
+        b.iter(|| {
+            let grid: EsriASCIIReader<_, f64, f64> =
+                EsriASCIIReader::from_file(Cursor::new(data.as_slice())).unwrap();
+            grid.into_iter()
+                .map(|cell| cell.unwrap().2)
+                .fold(0.0, |sum, value| sum + value)
+        });
+    });
+    group.bench_function("iterate f32", |b| {
+        b.iter(|| {
+            let grid: EsriASCIIReader<_, f64, f32> =
+                EsriASCIIReader::from_file(Cursor::new(data.as_slice())).unwrap();
+            grid.into_iter()
+                .map(|cell| cell.unwrap().2)
+                .fold(0.0, |sum, value| sum + value)
+        });
+    });
+    // Every row is parsed again for each cell, as it would be for scattered reads on a large grid
+    let mut grid: EsriASCIIReader<_, f64, f64> =
+        EsriASCIIReader::from_file(Cursor::new(data.as_slice()))
+            .unwrap()
+            .with_cache_policy(CachePolicy::Disabled);
+    let (nrows, ncols) = (grid.header.num_rows(), grid.header.num_cols());
+    group.bench_function("get_index uncached", |b| {
+        b.iter(|| {
+            for row in (0..nrows).step_by(50) {
+                black_box(grid.get_index(row, row % ncols).unwrap());
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -1,31 +1,58 @@
 use std::{
-    io::{self, BufRead, BufReader, Lines, Read, Seek, SeekFrom},
+    collections::{BTreeMap, btree_map::Entry},
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    ops::{Bound, Range, RangeBounds},
+    path::Path,
     vec::IntoIter,
 };
 
 use num_traits::NumCast;
 use replace_with::replace_with_or_abort;
 
+#[cfg(feature = "fast-parse")]
+use crate::fast_parse;
+#[cfg(feature = "rayon")]
+use crate::parallel;
 use crate::{
+    cache::{CachePolicy, CacheStats, RowCache},
     error::{self, Error},
     header::{EsriASCIIRasterHeader, Numerical},
+    index::RowIndex,
+    interpolate::{self, CellRegistration, Interpolation, NodataPolicy},
+    raster::EsriASCIIRaster,
+    shared::SharedEsriASCIIReader,
+    tokenizer::RowTokenizer,
+    writer::EsriASCIIWriter,
 };
 
 #[derive(Debug)]
-struct LineSeeker {
-    line: usize,
-    position: u64,
+pub(crate) struct LineSeeker {
+    pub(crate) line: usize,
+    pub(crate) position: u64,
 }
 impl LineSeeker {
-    fn update(&mut self, line: usize, position: u64) {
+    pub(crate) fn update(&mut self, line: usize, position: u64) {
         self.line = line;
         self.position = position;
     }
 }
 
+/// How the values of the grid are laid out across the lines of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DataLayout {
+    /// Every row of the grid is on its own line. A line with the wrong number of values is an error.
+    #[default]
+    Lines,
+    /// The values are a single whitespace separated stream, so a row may wrap across several lines,
+    /// or several rows may share a line. Cell N is row `N / ncols`, column `N % ncols`.
+    Stream,
+}
+
 /// A reader for ESRI ASCII raster files.
 /// This reader reads the header of the file and then reads the data on demand.
-/// The data is cached in memory, so that the file is only read once.
+/// The data is cached in memory according to the reader's `CachePolicy`, so that by default the file is only read once.
 ///
 /// # Type Parameters
 /// * `R` - The type of the file. This should be a file that implements `Read` and `Seek`.
@@ -35,10 +62,12 @@ impl LineSeeker {
 pub struct EsriASCIIReader<R, T: Numerical, U: Numerical> {
     pub header: EsriASCIIRasterHeader<T, U>,
     reader: BufReader<R>,
-    line_cache: Vec<Option<Vec<U>>>,
+    line_cache: RowCache<U>,
     line_start_cache: Vec<Option<u64>>,
     data_start: u64,
     line_seeker: LineSeeker,
+    layout: DataLayout,
+    registration: CellRegistration,
 }
 impl<R, T, U> EsriASCIIReader<R, T, U>
 where
@@ -79,15 +108,146 @@ where
         Ok(Self {
             header: grid_header,
             reader,
-            line_cache: vec![None; grid_header.num_rows()],
+            line_cache: RowCache::new(grid_header.num_rows(), CachePolicy::Unbounded),
             line_start_cache,
             data_start,
             line_seeker: LineSeeker {
                 line: 0,
                 position: data_start,
             },
+            layout: DataLayout::Lines,
+            registration: grid_header.cell_registration(),
         })
     }
+    /// Sets how the values of the grid are laid out across the lines of the file.
+    ///
+    /// By default every row is expected on its own line. Use `DataLayout::Stream` for files whose rows wrap across lines.
+    /// The position of each row is still cached as the file is read, so random access stays fast.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::{DataLayout, EsriASCIIReader};
+    /// use std::io::Cursor;
+    /// let file = Cursor::new("ncols 3\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3 4\n5 6\n");
+    /// let mut grid: EsriASCIIReader<_, f64, f64> = EsriASCIIReader::from_file(file)
+    ///     .unwrap()
+    ///     .with_layout(DataLayout::Stream);
+    /// assert_eq!(grid.get_index(1, 0).unwrap(), 4.0);
+    /// ```
+    #[must_use]
+    pub fn with_layout(mut self, layout: DataLayout) -> Self {
+        if layout != self.layout {
+            // Row positions and values cached under the old layout are no longer valid
+            self.line_cache.clear();
+            self.line_start_cache.fill(None);
+            if let Some(first) = self.line_start_cache.first_mut() {
+                *first = Some(self.data_start);
+            }
+            self.line_seeker.update(0, self.data_start);
+            self.layout = layout;
+        }
+        self
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Sets how many decoded rows are kept in memory. Any rows cached so far are dropped.
+    ///
+    /// By default every row that is read is kept, which is fastest but may end up holding the whole grid.
+    /// A bounded policy evicts the least recently used rows once its limit is reached.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::{ascii_file::EsriASCIIReader, cache::CachePolicy};
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file)
+    ///     .unwrap()
+    ///     .with_cache_policy(CachePolicy::MaxRows(1));
+    /// grid.get_index(0, 2).unwrap();
+    /// grid.get_index(1, 2).unwrap();
+    /// grid.get_index(1, 3).unwrap();
+    /// let stats = grid.cache_stats();
+    /// assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 2, 1));
+    /// ```
+    #[must_use]
+    pub fn with_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.line_cache = RowCache::new(self.header.num_rows(), policy);
+        self
+    }
+    /// Returns how many decoded rows are kept in memory.
+    pub fn cache_policy(&self) -> CachePolicy {
+        self.line_cache.policy()
+    }
+    /// Returns the hit, miss and eviction counts of the row cache, along with its current size.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.line_cache.stats()
+    }
+    /// Sets how interpolation treats the edges of the grid, overriding the registration given by the header.
+    ///
+    /// By default, grids positioned with `xllcorner` are `CellRegistration::PixelIsArea`, which samples the whole extent of the grid,
+    /// and grids positioned with `xllcenter` are `CellRegistration::PixelIsPoint`, which only samples between the outermost centres.
+    ///
+    /// The setting is kept by rasters read from this reader, and by `into_shared`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use esri_ascii_grid::interpolate::CellRegistration;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// // Within half a cell of the bottom edge, the bottom row is used
+    /// assert_eq!(grid.get_interpolate(125.0, 10.0), Some(1.0));
+    /// let mut grid = grid.with_cell_registration(CellRegistration::PixelIsPoint);
+    /// assert_eq!(grid.get_interpolate(125.0, 10.0), None);
+    /// ```
+    #[must_use]
+    pub fn with_cell_registration(mut self, registration: CellRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+    /// Returns how interpolation treats the edges of the grid.
+    pub fn cell_registration(&self) -> CellRegistration {
+        self.registration
+    }
+    /// Returns the byte offset of the start of every row, scanning the rest of the file if the offsets are not all known yet.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, or has fewer rows than the header says.
+    ///
+    /// # Panics
+    /// Panics if a row offset is missing after scanning, which should not happen as every row before the last is recorded while scanning.
+    pub fn row_offsets(&mut self) -> Result<Vec<u64>, Error> {
+        if let Some(last) = self.header.nrows.checked_sub(1)
+            && self.line_start_cache[last].is_none()
+        {
+            seek_to_line(
+                &mut self.reader,
+                last,
+                self.layout,
+                self.header.ncols,
+                &mut self.line_seeker,
+                &mut self.line_start_cache,
+            )?;
+        }
+        Ok(self
+            .line_start_cache
+            .iter()
+            .map(|start| start.unwrap())
+            .collect())
+    }
+    /// Uses previously found row offsets, so that any row can be read without scanning the file.
+    fn apply_row_index(&mut self, index: &RowIndex) {
+        self.layout = index.layout;
+        for (start, &offset) in self.line_start_cache.iter_mut().zip(&index.offsets) {
+            *start = Some(offset);
+        }
+        if let Some(&last) = index.offsets.last() {
+            self.line_seeker.update(index.offsets.len() - 1, last);
+        }
+    }
     /// Returns the value at the given row and column.
     /// 0, 0 is the top left corner. The row and column are zero indexed.
     /// # Examples
@@ -110,34 +270,465 @@ where
         if row >= self.header.nrows || col >= self.header.ncols {
             Err(crate::error::Error::OutOfBounds(row, col))?;
         }
-        if let Some(values) = &self.line_cache[row] {
-            let val = values[col];
-            return Ok(val);
+        let values = self.load_row(row)?;
+        Ok(values[col])
+    }
+    /// Reads every cell of the grid into an owned, in-memory `EsriASCIIRaster`.
+    ///
+    /// Rows that are already cached are not read again, and rows read by this function are not added to the cache.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let raster = grid.load_all().unwrap();
+    /// assert_eq!(raster.get_index(2, 2).unwrap(), grid.get_index(2, 2).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, or if any row has the wrong number of values or a value that is not a valid number.
+    pub fn load_all(&mut self) -> Result<EsriASCIIRaster<T, U>, Error> {
+        let mut data = Vec::with_capacity(self.header.nrows * self.header.ncols);
+        for row in 0..self.header.nrows {
+            if let Some(values) = self.line_cache.get(row) {
+                data.extend_from_slice(values);
+            } else {
+                data.extend(self.read_row(row)?);
+            }
+        }
+        Ok(EsriASCIIRaster::new(self.header, data)?.with_cell_registration(self.registration))
+    }
+    /// Reads the cells in the given rows and columns into memory, returning them as a raster with its own header.
+    ///
+    /// The header of the window is given by `EsriASCIIRasterHeader::window`. Only the rows in the window are read,
+    /// through the row cache.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let window = grid.read_window(1..3, 2..).unwrap();
+    /// assert_eq!(window.data(), [100.0, 36.0, 35.0, 10.0]);
+    /// assert_eq!((window.header.min_x(), window.header.min_y()), (100.0, 150.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the window is empty or extends beyond the grid, or if a row in it cannot be read.
+    pub fn read_window(
+        &mut self,
+        rows: impl RangeBounds<usize>,
+        cols: impl RangeBounds<usize>,
+    ) -> Result<EsriASCIIRaster<T, U>, Error> {
+        let rows = to_range(&rows, self.header.nrows);
+        let cols = to_range(&cols, self.header.ncols);
+        if rows.end > self.header.nrows || cols.end > self.header.ncols {
+            Err(Error::OutOfBounds(rows.end, cols.end))?;
+        }
+        let Some(header) = self.header.window(rows.clone(), cols.clone()) else {
+            return Err(Error::EmptyWindow(rows, cols));
+        };
+        let mut data = Vec::with_capacity(header.nrows * header.ncols);
+        for row in rows {
+            data.extend_from_slice(&self.load_row(row)?[cols.clone()]);
         }
+        Ok(EsriASCIIRaster::new(header, data)?.with_cell_registration(self.registration))
+    }
+    /// Reads the cells that overlap the given extent into memory, returning them as a raster with its own header.
+    ///
+    /// The extent is clipped to the grid and snapped outwards to whole cells, as in `EsriASCIIRasterHeader::window_of`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let window = grid.read_bbox(120.0, 160.0, 200.0, 240.0).unwrap();
+    /// assert_eq!(window.data(), [100.0, 36.0, 35.0, 10.0]);
+    /// assert_eq!((window.header.max_x(), window.header.max_y()), (200.0, 250.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the extent does not overlap the grid, or if a row in it cannot be read.
+    pub fn read_bbox(
+        &mut self,
+        min_x: T,
+        min_y: T,
+        max_x: T,
+        max_y: T,
+    ) -> Result<EsriASCIIRaster<T, U>, Error> {
+        let Some((rows, cols)) = self.header.window_of(min_x, min_y, max_x, max_y) else {
+            let (x, y) = extent(min_x, min_y, max_x, max_y);
+            return Err(Error::ExtentOutOfBounds(x, y));
+        };
+        self.read_window(rows, cols)
+    }
+    /// Clips the grid to the cells that overlap the given extent, writing them to `writer` as a new grid.
+    ///
+    /// The extent is clipped to the grid and snapped outwards to whole cells, as in `read_bbox`, and the header is
+    /// moved to match. Rows are read one at a time without being cached, so the clipped grid is never held in memory.
+    /// Returns the writer once the grid has been written.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let clipped = grid.clip_to(Vec::new(), 120.0, 160.0, 200.0, 240.0).unwrap();
+    /// let text = String::from_utf8(clipped).unwrap();
+    /// assert!(text.starts_with("ncols         2\nnrows         2\nxllcorner     100\nyllcorner     150\n"));
+    /// assert!(text.ends_with("100 36\n35 10\n"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the extent does not overlap the grid, or if a row cannot be read or written.
+    pub fn clip_to<W: Write>(
+        &mut self,
+        writer: W,
+        min_x: T,
+        min_y: T,
+        max_x: T,
+        max_y: T,
+    ) -> Result<W, Error>
+    where
+        T: Display,
+        U: Display,
+    {
+        let header = self.header;
+        let window = header
+            .window_of(min_x, min_y, max_x, max_y)
+            .and_then(|(rows, cols)| Some((rows.start, cols.start, header.window(rows, cols)?)));
+        let Some((row, col, clipped)) = window else {
+            let (x, y) = extent(min_x, min_y, max_x, max_y);
+            return Err(Error::ExtentOutOfBounds(x, y));
+        };
+        self.write_clip(writer, clipped, row.cast_signed(), col.cast_signed())
+    }
+    /// Clips the grid to the cells that overlap the given extent, writing them to `writer` as a new grid.
+    ///
+    /// Unlike `clip_to`, the extent is not clipped to the grid. It is snapped outwards to the cells of the grid extended
+    /// in every direction, and cells outside of the grid are written as nodata, so the extent does not need to overlap the grid at all.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let clipped = grid.clip_to_padded(Vec::new(), 120.0, 160.0, 250.0, 240.0).unwrap();
+    /// let text = String::from_utf8(clipped).unwrap();
+    /// assert!(text.starts_with("ncols         3\nnrows         2\n"));
+    /// assert!(text.ends_with("100 36 -9999\n35 10 -9999\n"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the minimum of the extent is greater than its maximum, if the extent is not finite or the clipped
+    /// grid cannot be positioned with type `T`, if the grid has no nodata value and the extent is not within the grid,
+    /// or if a row cannot be read or written.
+    pub fn clip_to_padded<W: Write>(
+        &mut self,
+        writer: W,
+        min_x: T,
+        min_y: T,
+        max_x: T,
+        max_y: T,
+    ) -> Result<W, Error>
+    where
+        T: Display,
+        U: Display,
+    {
+        let (x, y) = extent(min_x, min_y, max_x, max_y);
+        if min_x > max_x || min_y > max_y {
+            return Err(Error::InvertedExtent(x, y));
+        }
+        let header = self.header;
+        let to_index = |cell: f64| -> Option<isize> { NumCast::from(cell) };
+        let window = header
+            .cells_of(min_x, min_y, max_x, max_y)
+            .and_then(|(rows, cols)| {
+                let rows = to_index(rows.start)?..to_index(rows.end)?;
+                let cols = to_index(cols.start)?..to_index(cols.end)?;
+                let nrows = rows.end.checked_sub(rows.start)?.cast_unsigned();
+                let ncols = cols.end.checked_sub(cols.start)?.cast_unsigned();
+                let xll = header.xll + header.dx * T::from(cols.start)?;
+                let yur = header.yur - header.dy * T::from(rows.start)?;
+                let clipped = EsriASCIIRasterHeader {
+                    ncols,
+                    nrows,
+                    xll,
+                    yll: yur - header.dy * T::from(nrows)?,
+                    xur: xll + header.dx * T::from(ncols)?,
+                    yur,
+                    ..header
+                };
+                Some((rows, cols, clipped))
+            });
+        let Some((rows, cols, clipped)) = window else {
+            return Err(Error::InvalidExtent(x, y));
+        };
+        let padded = rows.start < 0
+            || cols.start < 0
+            || rows.end > header.nrows.cast_signed()
+            || cols.end > header.ncols.cast_signed();
+        if padded && header.nodata_value.is_none() {
+            return Err(Error::PaddingWithoutNodata(rows, cols));
+        }
+        self.write_clip(writer, clipped, rows.start, cols.start)
+    }
+    /// Writes the grid described by `header`, whose top left cell is at `row_offset`, `col_offset` in this grid.
+    /// Cells outside of this grid are written as nodata.
+    fn write_clip<W: Write>(
+        &mut self,
+        writer: W,
+        header: EsriASCIIRasterHeader<T, U>,
+        row_offset: isize,
+        col_offset: isize,
+    ) -> Result<W, Error>
+    where
+        T: Display,
+        U: Display,
+    {
+        let ncols = self.header.ncols.cast_signed();
+        let nrows = self.header.nrows.cast_signed();
+        let first_col = col_offset.clamp(0, ncols);
+        let last_col = (col_offset + header.ncols.cast_signed()).clamp(0, ncols);
+        // Where the columns read from this grid go in each written row
+        let dest = (first_col - col_offset).cast_unsigned();
+        let cols = first_col.cast_unsigned()..last_col.cast_unsigned();
+        let fill = header.nodata_value.unwrap_or_else(U::zero);
+        let mut values = vec![fill; header.ncols];
+        let mut writer = EsriASCIIWriter::new(writer, header);
+        for row in 0..header.nrows {
+            let source = row.cast_signed() + row_offset;
+            if (0..nrows).contains(&source) && !cols.is_empty() {
+                let source = source.cast_unsigned();
+                let row = match self.line_cache.get(source) {
+                    Some(row) => &row[cols.clone()],
+                    None => &self.read_row(source)?[cols.clone()],
+                };
+                values[dest..dest + cols.len()].copy_from_slice(row);
+                writer.write_row(&values)?;
+                values.fill(fill);
+            } else {
+                writer.write_row(&values)?;
+            }
+        }
+        writer.finish()
+    }
+    /// Returns an iterator over the rows of the grid from top to bottom, yielding the index and values of each row.
+    ///
+    /// Rows are read through the row cache, so cached rows are not read again and the rows read are cached according to
+    /// the reader's `CachePolicy`. The reader can be used again once the iterator is dropped.
+    /// Use `EsriASCIIRows::next_row` to borrow each row rather than copying it.
+    ///
+    /// If an error is encountered at any point, the iterator will return an `Err` once and halt.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let mut rows = grid.rows();
+    /// while let Some(row) = rows.next_row() {
+    ///     let (row, values) = row.unwrap();
+    ///     assert_eq!(values.len(), 4);
+    /// }
+    /// // Every row is now cached
+    /// assert_eq!(grid.get_index(5, 2).unwrap(), 1.0);
+    /// assert_eq!(grid.cache_stats().hits, 1);
+    /// ```
+    pub fn rows(&mut self) -> EsriASCIIRows<'_, R, T, U> {
+        EsriASCIIRows {
+            reader: self,
+            row: 0,
+            terminated: false,
+        }
+    }
+    /// Returns an iterator over the values in the raster that borrows the reader, rather than consuming it like `into_iter`.
+    ///
+    /// The cells are visited in the same order as `into_iter`. Rows are read through the row cache, and the start of every
+    /// row is remembered, so once the iterator is dropped, random access with `get_index` needs no scanning.
+    ///
+    /// If an error is encountered at any point, the iterator will return an `Err` once and halt.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let header = grid.header;
+    /// let total: f64 = grid
+    ///     .iter()
+    ///     .filter_map(|cell| header.mask(cell.unwrap().2))
+    ///     .sum();
+    /// assert_eq!(total, 567.0);
+    /// assert_eq!(grid.get_index(5, 2).unwrap(), 1.0);
+    /// ```
+    pub fn iter(&mut self) -> EsriASCIICells<'_, R, T, U> {
+        self.iter_region(.., ..)
+    }
+    /// Returns an iterator over the values of the cells in the given rows and columns, from left to right, top to bottom.
+    ///
+    /// Behaves the same as `iter`, but only the rows in the region are read. If the region extends beyond the grid,
+    /// the iterator returns an `OutOfBounds` error.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let cells = grid.iter_region(1..3, 2..).collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(cells, [(1, 2, 100.0), (1, 3, 36.0), (2, 2, 35.0), (2, 3, 10.0)]);
+    /// ```
+    pub fn iter_region(
+        &mut self,
+        rows: impl RangeBounds<usize>,
+        cols: impl RangeBounds<usize>,
+    ) -> EsriASCIICells<'_, R, T, U> {
+        let rows = to_range(&rows, self.header.nrows);
+        let cols = to_range(&cols, self.header.ncols);
+        let error = (rows.end > self.header.nrows || cols.end > self.header.ncols)
+            .then_some(Error::OutOfBounds(rows.end, cols.end));
+        EsriASCIICells {
+            reader: self,
+            row: rows.start,
+            row_it: None,
+            rows,
+            cols,
+            error,
+            terminated: false,
+        }
+    }
+    /// Turns the reader into an iterator over the rows of the grid from top to bottom, yielding the index and values of each row.
+    ///
+    /// Rows that are already cached are moved out of the cache rather than read again.
+    ///
+    /// If an error is encountered at any point, the iterator will return an `Err` once and halt.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let (row, values) = grid.into_rows().last().unwrap().unwrap();
+    /// assert_eq!(row, 5);
+    /// assert_eq!(values, [13.0, 5.0, 1.0, -9999.0]);
+    /// ```
+    pub fn into_rows(self) -> EsriASCIIRowsIntoIterator<R, T, U> {
+        EsriASCIIRowsIntoIterator {
+            header: self.header,
+            reader: self,
+            row: 0,
+            terminated: false,
+        }
+    }
+    /// Reads every cell of the grid into an owned, in-memory `EsriASCIIRaster`, parsing rows on every thread of the rayon pool.
+    ///
+    /// The whole data section is read into memory first, so this needs room for the text of the grid as well as its values.
+    /// The start of every row is cached along the way, so later random reads never scan the file.
+    ///
+    /// Only available with the `rayon` feature.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let raster = grid.load_all_parallel().unwrap();
+    /// assert_eq!(raster, grid.load_all().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, or if any row is missing, has the wrong number of values or a value that is not a valid number.
+    #[cfg(feature = "rayon")]
+    pub fn load_all_parallel(&mut self) -> Result<EsriASCIIRaster<T, U>, Error>
+    where
+        U: Send + Sync,
+    {
+        let mut data = Vec::new();
+        seek_to(&mut self.reader, self.data_start)?;
+        self.reader.read_to_end(&mut data)?;
+        let (values, row_starts) =
+            parallel::parse_grid(&data, self.layout, self.header.nrows, self.header.ncols)?;
+        for (row, (cache, start)) in self.line_start_cache.iter_mut().zip(row_starts).enumerate() {
+            let position = self.data_start + start as u64;
+            *cache = Some(position);
+            self.line_seeker.update(row, position);
+        }
+        Ok(EsriASCIIRaster::new(self.header, values)?.with_cell_registration(self.registration))
+    }
+    /// Returns the values of the given row, reading and caching it if it is not cached yet.
+    fn load_row(&mut self, row: usize) -> Result<&[U], Error> {
+        if self.line_cache.touch(row) {
+            return Ok(self.line_cache.get(row).unwrap());
+        }
+        let values = self.read_row(row)?;
+        Ok(self.line_cache.insert(row, values))
+    }
+    /// Reads and parses the given row from the file, bypassing the cache.
+    fn read_row(&mut self, row: usize) -> Result<Vec<U>, Error> {
         let reader = self.reader.by_ref();
-        let line = if let Some(line_pos) = self.line_start_cache[row] {
-            reader.seek(SeekFrom::Start(line_pos))?;
-            reader.lines().next().unwrap()?
+        if let Some(line_pos) = self.line_start_cache[row] {
+            seek_to(reader, line_pos)?;
         } else {
             seek_to_line(
                 reader,
                 row,
+                self.layout,
+                self.header.ncols,
                 &mut self.line_seeker,
                 &mut self.line_start_cache,
             )?;
-            reader.lines().next().unwrap()?
-        };
-        let value_res = line
-            .split_whitespace()
-            .map(|s| s.parse::<U>().map_err(|_| Error::TypeCast(
-                format!("{row}, {col}"),
-                "grid value".to_owned(),
-                std::any::type_name::<U>(),
-            )));
-        let values: Vec<U> = value_res.collect::<Result<Vec<U>, Error>>()?;
-        let ret = values[col];
-        self.line_cache[row] = Some(values);
-        Ok(ret)
+        }
+        let mut line = String::new();
+        let values = read_next_row(reader, self.layout, row, self.header.ncols, &mut line)?
+            .ok_or(Error::MismatchedRowCount(self.header.nrows, row))?;
+        // Remember where the next row starts, so reading the grid in order never scans a row twice
+        let next_row = row + 1;
+        if next_row < self.header.nrows && self.line_start_cache[next_row].is_none() {
+            let position = reader.stream_position()?;
+            self.line_start_cache[next_row] = Some(position);
+            if self.line_seeker.line < next_row {
+                self.line_seeker.update(next_row, position);
+            }
+        }
+        Ok(values)
+    }
+    /// Returns the value at the given row and column, or nothing if the cell is nodata.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// assert_eq!(grid.get_index_masked(0, 0).unwrap(), None);
+    /// assert_eq!(grid.get_index_masked(0, 2).unwrap(), Some(5.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    pub fn get_index_masked(&mut self, row: usize, col: usize) -> Result<Option<U>, Error> {
+        let value = self.get_index(row, col)?;
+        Ok(self.header.mask(value))
+    }
+    /// Returns the value at the given x and y coordinates, or nothing if they are out of bounds or the cell is nodata.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub fn get_masked(&mut self, x: T, y: T) -> Option<U> {
+        let value = self.get(x, y)?;
+        self.header.mask(value)
     }
     /// Returns the value at the given x and y coordinates.
     ///
@@ -170,9 +761,12 @@ where
     ///
     /// If the coordinates are outside the bounds of the raster, nothing is returned.
     ///
-    /// The value is interpolated from the four nearest cells.
+    /// The value of each cell lies at its centre, and the value is interpolated from the four nearest centres.
+    /// How the edges of the grid are handled depends on the reader's `CellRegistration`.
+    /// Nodata cells are left out of the interpolation, and nothing is returned if every contributing cell is nodata.
+    /// See `get_interpolate_with` for other behaviours.
     ///
-    /// Even if the coordinates are exactly on a cell, the value is interpolated and so may or may not be the same as the value at the cell due to floating point errors.
+    /// Even if the coordinates are exactly on the centre of a cell, the value is interpolated and so may or may not be the same as the value at the cell due to floating point errors.
     ///
     /// # Examples
     /// ```rust
@@ -182,41 +776,219 @@ where
     /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();;
     /// // Spot check a few values
     /// assert_eq!(grid.get_interpolate(390000.0, 344000.0).unwrap(), 141.2700042724609375);
-    /// assert_eq!(grid.get_interpolate(390003.0, 344003.0).unwrap(), 135.44000244140625);
+    /// assert_eq!(grid.get_interpolate(390003.5, 344003.5).unwrap(), 135.44000244140625);
     /// ```
     ///
     /// # Panics
-    /// Panics if the coordinates are outside the bounds of the raster, which should not happen as they are checked in this function.
+    /// Panics if a contributing cell cannot be read. Use `get_interpolate_with` to handle the error instead.
     pub fn get_interpolate(&mut self, x: T, y: T) -> Option<U> {
-        if x < self.header.min_x()
-            || x > self.header.max_x()
-            || y < self.header.min_y()
-            || y > self.header.max_y()
-        {
-            return None;
-        }
-        let (mut ll_row, mut ll_col) = self.header.index_of(x, y).unwrap();
-        ll_col = ll_col.min(self.header.num_cols() - 2);
-        ll_row = ll_row.max(1);
-
-        let (ll_x, ll_y) = self.header.index_pos(ll_row, ll_col).unwrap();
-
-        let ll = <f64 as NumCast>::from(self.get_index(ll_row, ll_col).unwrap()).unwrap();
-        let lr = <f64 as NumCast>::from(self.get_index(ll_row, ll_col + 1).unwrap()).unwrap();
-        let ul = <f64 as NumCast>::from(self.get_index(ll_row - 1, ll_col).unwrap()).unwrap();
-        let ur = <f64 as NumCast>::from(self.get_index(ll_row - 1, ll_col + 1).unwrap()).unwrap();
-
-        let cell_size = <f64 as NumCast>::from(self.header.cell_size()).unwrap();
-        let vert_weight = <f64 as NumCast>::from(x - ll_x).unwrap() / cell_size;
-        let horiz_weight = <f64 as NumCast>::from(y - ll_y).unwrap() / cell_size;
-
-        let ll_weight = (1.0 - vert_weight) * (1.0 - horiz_weight);
-        let ur_weight = vert_weight * horiz_weight;
-        let ul_weight = (1.0 - vert_weight) * horiz_weight;
-        let lr_weight = vert_weight * (1.0 - horiz_weight);
+        self.get_interpolate_with(x, y, NodataPolicy::default())
+            .unwrap()
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Nodata cells that would contribute to the result are handled according to `policy`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use esri_ascii_grid::interpolate::NodataPolicy;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// // Three of the four nearest cells are nodata
+    /// assert_eq!(grid.get_interpolate_with(40.0, 260.0, NodataPolicy::Refuse).unwrap(), None);
+    /// assert_eq!(grid.get_interpolate_with(40.0, 260.0, NodataPolicy::Skip).unwrap(), Some(20.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn get_interpolate_with(
+        &mut self,
+        x: T,
+        y: T,
+        policy: NodataPolicy,
+    ) -> Result<Option<U>, Error> {
+        let header = self.header;
+        interpolate::bilinear(&header, self.registration, x, y, policy, |row, col| {
+            self.get_index(row, col)
+        })
+    }
+    /// Returns the value at the given x and y coordinates, sampled from the nearby cells using `method`.
+    ///
+    /// If the coordinates are outside the bounds of the raster, nothing is returned.
+    /// Nodata cells are handled as in `get_interpolate`; see `Interpolation` for the details of each method.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use esri_ascii_grid::interpolate::Interpolation;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// // The value of each cell lies at its centre
+    /// assert_eq!(grid.sample(125.0, 175.0, Interpolation::Bicubic).unwrap(), Some(35.0));
+    /// assert_eq!(grid.sample(90.0, 210.0, Interpolation::Nearest).unwrap(), grid.get(90.0, 210.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn sample(&mut self, x: T, y: T, method: Interpolation) -> Result<Option<U>, Error> {
+        let header = self.header;
+        interpolate::sample(
+            &header,
+            self.registration,
+            x,
+            y,
+            method,
+            NodataPolicy::default(),
+            |row, col| self.get_index(row, col),
+        )
+    }
+    /// Samples the grid at each of the given x and y coordinates using `method`, returning the values in the same order.
+    ///
+    /// Each value is the same as that returned by `sample`, but the coordinates are visited from the top of the grid to
+    /// the bottom, so every row that is needed is read once, in order, no matter how the coordinates are ordered.
+    /// Rows are read without being cached, and only the rows needed by nearby coordinates are held in memory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use esri_ascii_grid::interpolate::Interpolation;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let points = [(125.0, 25.0), (125.0, 175.0), (-10.0, 0.0), (175.0, 225.0)];
+    /// let values = grid.sample_many(&points, Interpolation::Nearest).unwrap();
+    /// assert_eq!(values, [Some(1.0), Some(35.0), None, Some(36.0)]);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a contributing row cannot be read.
+    pub fn sample_many(
+        &mut self,
+        points: &[(T, T)],
+        method: Interpolation,
+    ) -> Result<Vec<Option<U>>, Error> {
+        let header = self.header;
+        let registration = self.registration;
+        let mut queries = points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(x, y))| {
+                Some((
+                    interpolate::rows_needed(&header, registration, x, y, method)?,
+                    i,
+                ))
+            })
+            .collect::<Vec<_>>();
+        queries.sort_unstable_by_key(|(rows, _)| *rows.start());
 
-        let value: f64 = ul * ul_weight + ur * ur_weight + ll * ll_weight + lr * lr_weight;
-        Some(U::from(value).unwrap())
+        let mut values = vec![None; points.len()];
+        // The rows read so far; the queries are sorted by their first row, so rows above it are never needed again
+        let mut rows: BTreeMap<usize, Vec<U>> = BTreeMap::new();
+        for (needed, i) in queries {
+            rows = rows.split_off(needed.start());
+            for row in needed {
+                if let Entry::Vacant(entry) = rows.entry(row) {
+                    entry.insert(match self.line_cache.get(row) {
+                        Some(values) => values.to_vec(),
+                        None => self.read_row(row)?,
+                    });
+                }
+            }
+            let (x, y) = points[i];
+            values[i] = interpolate::sample(
+                &header,
+                registration,
+                x,
+                y,
+                method,
+                NodataPolicy::default(),
+                |row, col| {
+                    rows.get(&row)
+                        .and_then(|values| values.get(col))
+                        .copied()
+                        .ok_or(Error::OutOfBounds(row, col))
+                },
+            )?;
+        }
+        Ok(values)
+    }
+}
+impl<T, U> EsriASCIIReader<File, T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `EsriASCIIReader` from the file at `path`, using the row offsets saved in its `.idx` sidecar file.
+    ///
+    /// With the offsets loaded, reading any row is a single seek, even on the first call, and the layout recorded in the sidecar is used.
+    /// If the sidecar is missing, unreadable, was built from a different version of the file, or its offsets do not start at the
+    /// first row of the grid, it is ignored and the rows are found as they are read, the same as `from_file`.
+    /// Opening a grid never writes the sidecar; use `RowIndex::build_sidecar` to write it with the layout of the grid.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::{DataLayout, EsriASCIIReader};
+    /// use esri_ascii_grid::index::RowIndex;
+    /// let path = std::env::temp_dir().join("esri_ascii_grid_doc_index.asc");
+    /// std::fs::copy("test_data/test_llcorner.asc", &path).unwrap();
+    /// RowIndex::build_sidecar::<f64, f64>(&path, DataLayout::Lines).unwrap();
+    /// let mut grid: EsriASCIIReader<_, f64, f64> = EsriASCIIReader::from_file_with_index(&path).unwrap();
+    /// assert_eq!(grid.get_index(5, 2).unwrap(), 1.0);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the grid cannot be opened or its header cannot be read.
+    pub fn from_file_with_index(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut grid = Self::from_file(file)?;
+        let index = File::open(RowIndex::sidecar_path(path))
+            .ok()
+            .and_then(|sidecar| RowIndex::read(sidecar).ok())
+            .filter(|index| {
+                index.matches(&metadata)
+                    && index.offsets.len() == grid.header.nrows
+                    && index
+                        .offsets
+                        .first()
+                        .is_none_or(|&first| first == grid.data_start)
+            });
+        if let Some(index) = index {
+            grid.apply_row_index(&index);
+        }
+        Ok(grid)
+    }
+    /// Turns this reader into a `SharedEsriASCIIReader`, which can be used from many threads at once.
+    ///
+    /// The layout, cache policy, and every row offset and row found so far are kept.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::{ascii_file::EsriASCIIReader, cache::CachePolicy};
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file)
+    ///     .unwrap()
+    ///     .with_cache_policy(CachePolicy::MaxRows(2));
+    /// let grid = grid.into_shared();
+    /// assert_eq!(grid.get_index(5, 2).unwrap(), 1.0);
+    /// ```
+    pub fn into_shared(self) -> SharedEsriASCIIReader<T, U> {
+        SharedEsriASCIIReader::from_parts(
+            self.header,
+            self.reader.into_inner(),
+            self.layout,
+            self.line_start_cache,
+            self.line_seeker,
+            self.line_cache,
+        )
+        .with_cell_registration(self.registration)
     }
 }
 impl<R, T, U> IntoIterator for EsriASCIIReader<R, T, U>
@@ -268,14 +1040,15 @@ where
     /// ```
     ///
     fn into_iter(self) -> Self::IntoIter {
-        let line_reader = LineReader::Uninitialized {
+        let row_reader = RowReader::Uninitialized {
             data_start: self.data_start,
             reader: self.reader,
         };
 
         EsriASCIIRasterIntoIterator {
             header: self.header,
-            line_reader,
+            row_reader,
+            layout: self.layout,
             row_it: None,
             row: 0,
             col: 0,
@@ -283,34 +1056,151 @@ where
         }
     }
 }
-fn seek_to_line<R: Read + Seek>(
+/// Parses a line of whitespace separated values, which should contain exactly `ncols` values.
+pub(crate) fn parse_row<U>(line: &str, row: usize, ncols: usize) -> Result<Vec<U>, Error>
+where
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    let values = line
+        .split_whitespace()
+        .enumerate()
+        .map(|(col, s)| parse_value(s, row, col))
+        .collect::<Result<Vec<U>, Error>>()?;
+    if values.len() != ncols {
+        Err(Error::MismatchColumnCount(ncols, values.len()))?;
+    }
+    Ok(values)
+}
+/// Parses a single grid value.
+///
+/// With the `fast-parse` feature, primitive numbers are parsed with lexical-core, falling back to `FromStr` for anything it rejects.
+pub(crate) fn parse_value<U>(token: &str, row: usize, col: usize) -> Result<U, Error>
+where
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    #[cfg(feature = "fast-parse")]
+    if let Some(value) = fast_parse::parse(token) {
+        return Ok(value);
+    }
+    token.parse::<U>().map_err(|_| {
+        Error::TypeCast(
+            format!("{row}, {col}"),
+            "grid value".to_owned(),
+            std::any::type_name::<U>(),
+        )
+    })
+}
+/// Returns the x and y ranges covered by an extent, as reported in errors.
+fn extent<T: Numerical>(min_x: T, min_y: T, max_x: T, max_y: T) -> (Range<f64>, Range<f64>) {
+    let to_f64 = |value: T| -> f64 { NumCast::from(value).unwrap_or(f64::NAN) };
+    (to_f64(min_x)..to_f64(max_x), to_f64(min_y)..to_f64(max_y))
+}
+/// Finds the byte offset of the start of up to `nrows` rows of values laid out as `DataLayout::Stream`.
+///
+/// As when reading sequentially, a row starts just after the last value of the previous row, and only rows with at least one value are found.
+#[cfg_attr(not(any(feature = "mmap", feature = "rayon")), allow(dead_code))]
+pub(crate) fn stream_row_starts(data: &[u8], nrows: usize, ncols: usize) -> Vec<usize> {
+    // Every row takes at least two bytes, a value and the whitespace after it, except for the last
+    let mut row_starts = Vec::with_capacity(nrows.min(data.len() / 2 + 1));
+    let mut tokens = 0_usize;
+    let mut in_token = false;
+    let mut row_end = 0;
+    for (offset, byte) in data.iter().enumerate() {
+        let is_token = !byte.is_ascii_whitespace();
+        if is_token && !in_token {
+            if tokens.is_multiple_of(ncols) {
+                if row_starts.len() == nrows {
+                    break;
+                }
+                row_starts.push(row_end);
+            }
+            tokens += 1;
+        } else if !is_token && in_token && tokens.is_multiple_of(ncols) {
+            row_end = offset;
+        }
+        in_token = is_token;
+    }
+    row_starts
+}
+/// Reads the values of the next row, or nothing if there is no more data.
+///
+/// `line` is a scratch buffer that is reused between calls.
+pub(crate) fn read_next_row<B, U>(
+    reader: &mut B,
+    layout: DataLayout,
+    row: usize,
+    ncols: usize,
+    line: &mut String,
+) -> Result<Option<Vec<U>>, Error>
+where
+    B: BufRead,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    if !read_row_text(reader, layout, ncols, line)? {
+        return Ok(None);
+    }
+    parse_row(line, row, ncols).map(Some)
+}
+/// Reads the text of the next row into `line` without parsing it. Returns false if there is no more data.
+fn read_row_text<B: BufRead>(
+    reader: &mut B,
+    layout: DataLayout,
+    ncols: usize,
+    line: &mut String,
+) -> Result<bool, Error> {
+    let mut tokenizer = RowTokenizer::new(layout, ncols, line);
+    while !tokenizer.is_complete() {
+        let taken = tokenizer.feed(reader.fill_buf()?);
+        reader.consume(taken);
+    }
+    tokenizer.finish(line)
+}
+/// Seeks to an absolute position, keeping the buffered data if the position is already buffered.
+fn seek_to<R: Read + Seek>(reader: &mut BufReader<R>, position: u64) -> Result<(), Error> {
+    let current = reader.stream_position()?;
+    reader.seek_relative(position.cast_signed() - current.cast_signed())?;
+    Ok(())
+}
+pub(crate) fn seek_to_line<R: Read + Seek>(
     reader: &mut BufReader<R>,
     row: usize,
+    layout: DataLayout,
+    ncols: usize,
     line_seeker: &mut LineSeeker,
     line_start_cache: &mut [Option<u64>],
 ) -> Result<(), Error> {
     let latest_line = line_seeker.line;
     let latest_pos = line_seeker.position;
-    reader.seek(SeekFrom::Start(latest_pos))?;
-    for (cache, line) in line_start_cache[latest_line..row].iter_mut().zip(latest_line..) {
+    seek_to(reader, latest_pos)?;
+    let mut scratch = String::new();
+    for (cache, line) in line_start_cache[latest_line..row]
+        .iter_mut()
+        .zip(latest_line..)
+    {
         *cache = Some(reader.stream_position()?);
-        reader
-            .lines()
-            .next()
-            .ok_or(Error::MismatchedRowCount(row, line))??;
+        if !read_row_text(reader, layout, ncols, &mut scratch)? {
+            Err(Error::MismatchedRowCount(row, line))?;
+        }
     }
-    line_seeker.update(row, reader.stream_position()?);
+    let position = reader.stream_position()?;
+    line_start_cache[row] = Some(position);
+    line_seeker.update(row, position);
     Ok(())
 }
 
 #[derive(Debug)]
-enum LineReader<R> {
+enum RowReader<R> {
     Uninitialized {
         data_start: u64,
         reader: BufReader<R>,
     },
     Initialized {
-        lines: Lines<BufReader<R>>,
+        reader: BufReader<R>,
+        /// Scratch buffer reused for every row.
+        line: String,
     },
     /// Will reach this state if an error occurs during initialization.
     Invalid {
@@ -318,10 +1208,17 @@ enum LineReader<R> {
         error: Option<io::Error>,
     },
 }
-impl<R: Read + Seek> Iterator for LineReader<R> {
-    type Item = Result<String, io::Error>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<R: Read + Seek> RowReader<R> {
+    fn next_row<U>(
+        &mut self,
+        layout: DataLayout,
+        row: usize,
+        ncols: usize,
+    ) -> Option<Result<Vec<U>, Error>>
+    where
+        U: Numerical,
+        error::Error: From<<U as Numerical>::Err>,
+    {
         // try to initialize
         if matches!(self, Self::Uninitialized { .. }) {
             replace_with_or_abort(self, |r| {
@@ -332,18 +1229,17 @@ impl<R: Read + Seek> Iterator for LineReader<R> {
                 else {
                     unreachable!()
                 };
-                let convert = move || -> Result<Lines<BufReader<R>>, io::Error> {
-                    reader.seek(SeekFrom::Start(data_start))?;
-                    Ok(reader.lines())
-                };
-                match convert() {
-                    Ok(lines) => Self::Initialized { lines },
+                match reader.seek(SeekFrom::Start(data_start)) {
+                    Ok(_) => Self::Initialized {
+                        reader,
+                        line: String::new(),
+                    },
                     Err(err) => Self::Invalid { error: Some(err) },
                 }
             });
             if let Self::Invalid { error } = self {
                 let error = error.take().unwrap();
-                return Some(Err(error));
+                return Some(Err(error.into()));
             }
         }
 
@@ -353,7 +1249,9 @@ impl<R: Read + Seek> Iterator for LineReader<R> {
                 // error has been returned for the previous iteration, so we halt here
                 None
             }
-            Self::Initialized { lines } => lines.next(),
+            Self::Initialized { reader, line } => {
+                read_next_row(reader, layout, row, ncols, line).transpose()
+            }
         }
     }
 }
@@ -361,12 +1259,39 @@ impl<R: Read + Seek> Iterator for LineReader<R> {
 #[derive(Debug)]
 pub struct EsriASCIIRasterIntoIterator<R, T: Numerical, U: Numerical> {
     pub header: EsriASCIIRasterHeader<T, U>,
-    line_reader: LineReader<R>,
+    row_reader: RowReader<R>,
+    layout: DataLayout,
     row_it: Option<IntoIter<U>>,
     row: usize,
     col: usize,
     terminated: bool,
 }
+impl<R, T, U> EsriASCIIRasterIntoIterator<R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Turns this into an iterator that yields nothing in place of nodata values.
+    ///
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let nodata_cells = grid
+    ///     .into_iter()
+    ///     .masked()
+    ///     .filter(|cell| matches!(cell, Ok((_, _, None))))
+    ///     .count();
+    /// assert_eq!(nodata_cells, 4);
+    /// ```
+    pub fn masked(self) -> EsriASCIIRasterMaskedIterator<R, T, U> {
+        EsriASCIIRasterMaskedIterator { inner: self }
+    }
+}
 impl<R, T, U> Iterator for EsriASCIIRasterIntoIterator<R, T, U>
 where
     R: Read + Seek,
@@ -396,27 +1321,19 @@ where
 
         // load new row
         if self.row_it.is_none() {
-            match self.line_reader.next() {
-                Some(Ok(line)) => {
-                    match line
-                        .split_whitespace()
-                        .map(str::parse)
-                        .collect::<Result<Vec<_>, _>>()
-                    {
-                        Ok(row) => self.row_it = Some(row.into_iter()),
-                        Err(error) => {
-                            self.terminated = true;
-                            let _ = Result::<(usize, usize, U), Error>::Err(error.into());
-                        }
-                    }
-                }
+            match self
+                .row_reader
+                .next_row(self.layout, self.row, self.header.ncols)
+            {
+                Some(Ok(row)) => self.row_it = Some(row.into_iter()),
                 Some(Err(error)) => {
                     self.terminated = true;
-                    return Some(Err(error.into()));
+                    return Some(Err(error));
                 }
                 None => {
+                    // the file ended before the number of rows given in the header
                     self.terminated = true;
-                    return None;
+                    return Some(Err(Error::MismatchedRowCount(self.header.nrows, self.row)));
                 }
             }
         }
@@ -424,12 +1341,185 @@ where
         let current_col = self.col;
         let current_row = self.row;
 
-        // row_it is guaranteed to be Some here
-        let Some(value) = self.row_it.as_mut().unwrap().next() else {
-            return Some(Err(Error::MismatchColumnCount(self.header.ncols, self.col)));
-        };
+        // row_it is guaranteed to be Some here, and holds exactly ncols values
+        let value = self.row_it.as_mut().unwrap().next().unwrap();
         self.col += 1;
 
         Some(Ok((current_row, current_col, value)))
     }
 }
+
+/// Turns a range of indices into a `Range`, where an unbounded end is `len`.
+fn to_range(range: &impl RangeBounds<usize>, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    start..end
+}
+
+/// An iterator over the cells of a raster that borrows the reader. Created by `EsriASCIIReader::iter` and `EsriASCIIReader::iter_region`.
+#[derive(Debug)]
+pub struct EsriASCIICells<'a, R, T: Numerical, U: Numerical> {
+    reader: &'a mut EsriASCIIReader<R, T, U>,
+    rows: Range<usize>,
+    cols: Range<usize>,
+    row: usize,
+    /// The columns of the current row that are left to visit
+    row_it: Option<std::iter::Zip<Range<usize>, IntoIter<U>>>,
+    error: Option<Error>,
+    terminated: bool,
+}
+impl<R, T, U> Iterator for EsriASCIICells<'_, R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, usize, U), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.terminated {
+            return None;
+        }
+        if let Some(error) = self.error.take() {
+            self.terminated = true;
+            return Some(Err(error));
+        }
+        loop {
+            if let Some((col, value)) = self.row_it.as_mut().and_then(Iterator::next) {
+                return Some(Ok((self.row - 1, col, value)));
+            }
+            if self.row >= self.rows.end || self.cols.is_empty() {
+                self.terminated = true;
+                return None;
+            }
+            // Copy the columns of the region, so that the row is looked up in the cache only once
+            match self.reader.load_row(self.row) {
+                Ok(values) => {
+                    let values = values[self.cols.clone()].to_vec();
+                    self.row_it = Some(self.cols.clone().zip(values));
+                    self.row += 1;
+                }
+                Err(error) => {
+                    self.terminated = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the rows of a raster that borrows the reader. Created by `EsriASCIIReader::rows`.
+#[derive(Debug)]
+pub struct EsriASCIIRows<'a, R, T: Numerical, U: Numerical> {
+    reader: &'a mut EsriASCIIReader<R, T, U>,
+    row: usize,
+    terminated: bool,
+}
+impl<R, T, U> EsriASCIIRows<'_, R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Returns the index of the next row and a slice of its values, borrowed from the row cache, or nothing once every row has been read.
+    ///
+    /// Unlike `next`, the values are not copied.
+    pub fn next_row(&mut self) -> Option<Result<(usize, &[U]), Error>> {
+        if self.terminated || self.row >= self.reader.header.nrows {
+            self.terminated = true;
+            return None;
+        }
+        let row = self.row;
+        self.row += 1;
+        match self.reader.load_row(row) {
+            Ok(values) => Some(Ok((row, values))),
+            Err(error) => {
+                self.terminated = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+impl<R, T, U> Iterator for EsriASCIIRows<'_, R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, Vec<U>), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row()
+            .map(|row| row.map(|(row, values)| (row, values.to_vec())))
+    }
+}
+
+/// An iterator over the rows of a raster that owns the reader. Created by `EsriASCIIReader::into_rows`.
+#[derive(Debug)]
+pub struct EsriASCIIRowsIntoIterator<R, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    reader: EsriASCIIReader<R, T, U>,
+    row: usize,
+    terminated: bool,
+}
+impl<R, T, U> Iterator for EsriASCIIRowsIntoIterator<R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, Vec<U>), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.terminated || self.row >= self.header.nrows {
+            self.terminated = true;
+            return None;
+        }
+        let row = self.row;
+        self.row += 1;
+        let values = match self.reader.line_cache.take(row) {
+            Some(values) => Ok(values),
+            None => self.reader.read_row(row),
+        };
+        if values.is_err() {
+            self.terminated = true;
+        }
+        Some(values.map(|values| (row, values)))
+    }
+}
+
+/// An iterator over the cells of a raster that yields nothing in place of nodata values.
+/// Created by `EsriASCIIRasterIntoIterator::masked`.
+#[derive(Debug)]
+pub struct EsriASCIIRasterMaskedIterator<R, T: Numerical, U: Numerical> {
+    inner: EsriASCIIRasterIntoIterator<R, T, U>,
+}
+impl<R, T, U> Iterator for EsriASCIIRasterMaskedIterator<R, T, U>
+where
+    R: Read + Seek,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, usize, Option<U>), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let header = self.inner.header;
+        self.inner
+            .next()
+            .map(|cell| cell.map(|(row, col, value)| (row, col, header.mask(value))))
+    }
+}
@@ -0,0 +1,440 @@
+use std::{collections::BTreeMap, io::SeekFrom};
+
+use futures_util::stream::{self, Stream};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncSeek, AsyncSeekExt, BufReader};
+
+use crate::{
+    ascii_file::{DataLayout, LineSeeker, parse_row},
+    cache::{CachePolicy, CacheStats, RowCache},
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+    interpolate::{self, CellRegistration, Interpolation, NodataPolicy},
+    tokenizer::RowTokenizer,
+};
+
+/// An async reader for ESRI ASCII raster files that only reads forward, the async counterpart of `EsriASCIIStreamReader`.
+///
+/// Only available with the `tokio` feature.
+///
+/// # Type Parameters
+/// * `B` - The type of the source. This should implement `AsyncBufRead`.
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct AsyncEsriASCIIStreamReader<B, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    reader: B,
+    layout: DataLayout,
+}
+impl<B, T, U> AsyncEsriASCIIStreamReader<B, T, U>
+where
+    B: AsyncBufRead + Unpin,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `AsyncEsriASCIIStreamReader`, reading the header from the current position of `reader`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::async_reader::AsyncEsriASCIIStreamReader;
+    /// use futures_util::StreamExt;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let data = "ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3 4\n";
+    /// let grid: AsyncEsriASCIIStreamReader<_, f64, f64> =
+    ///     AsyncEsriASCIIStreamReader::new(data.as_bytes()).await.unwrap();
+    /// let rows = grid.into_rows().map(Result::unwrap).collect::<Vec<_>>().await;
+    /// assert_eq!(rows, [vec![1.0, 2.0], vec![3.0, 4.0]]);
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is something wrong with the header, such as missing values.
+    pub async fn new(mut reader: B) -> Result<Self, Error> {
+        let header = EsriASCIIRasterHeader::from_async_buf_read(&mut reader).await?;
+        Ok(Self {
+            header,
+            reader,
+            layout: DataLayout::Lines,
+        })
+    }
+    /// Sets how the values of the grid are laid out across the lines of the file.
+    #[must_use]
+    pub fn with_layout(mut self, layout: DataLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Turns this reader into a stream of the values of every row, from top to bottom.
+    ///
+    /// If an error is encountered at any point, the stream will return an `Err` once and halt.
+    pub fn into_rows(self) -> impl Stream<Item = Result<Vec<U>, Error>> {
+        let state = Some((self, 0, String::new()));
+        stream::unfold(state, |state| async move {
+            let (mut grid, row, mut line) = state?;
+            if row >= grid.header.nrows {
+                return None;
+            }
+            let (ncols, nrows) = (grid.header.ncols, grid.header.nrows);
+            match read_next_row(&mut grid.reader, grid.layout, row, ncols, &mut line).await {
+                Ok((Some(values), _)) => Some((Ok(values), Some((grid, row + 1, line)))),
+                // the data ended before the number of rows given in the header
+                Ok((None, _)) => Some((Err(Error::MismatchedRowCount(nrows, row)), None)),
+                Err(error) => Some((Err(error), None)),
+            }
+        })
+    }
+}
+
+/// An async reader for ESRI ASCII raster files with random access, the async counterpart of `EsriASCIIReader`.
+///
+/// Rows are found, read and cached in the same way as `EsriASCIIReader`.
+///
+/// Only available with the `tokio` feature.
+///
+/// # Type Parameters
+/// * `R` - The type of the file. This should implement `AsyncRead` and `AsyncSeek`.
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct AsyncEsriASCIIReader<R, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    reader: BufReader<R>,
+    /// The position of `reader`, tracked as it is read, because asking it for its position would drop its buffer.
+    /// `UNKNOWN_POSITION` after an error, so that the next read seeks.
+    position: u64,
+    line_cache: RowCache<U>,
+    line_start_cache: Vec<Option<u64>>,
+    data_start: u64,
+    line_seeker: LineSeeker,
+    layout: DataLayout,
+    registration: CellRegistration,
+    line: String,
+}
+impl<R, T, U> AsyncEsriASCIIReader<R, T, U>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `AsyncEsriASCIIReader` from a file. Only the header is read at first.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::async_reader::AsyncEsriASCIIReader;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let data = std::fs::read("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+    ///     AsyncEsriASCIIReader::from_file(std::io::Cursor::new(data)).await.unwrap();
+    /// assert_eq!(grid.get_index(5, 2).await.unwrap(), 1.0);
+    /// assert_eq!(grid.get(125.0, 275.0).await, Some(5.0));
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is something wrong with the header, such as missing values.
+    pub async fn from_file(file: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(file);
+        reader.rewind().await?;
+        let header = EsriASCIIRasterHeader::from_async_buf_read(&mut reader).await?;
+        let data_start = reader.stream_position().await?;
+        let mut line_start_cache = vec![None; header.num_rows()];
+        if let Some(first) = line_start_cache.first_mut() {
+            *first = Some(data_start);
+        }
+        Ok(Self {
+            header,
+            reader,
+            position: data_start,
+            line_cache: RowCache::new(header.num_rows(), CachePolicy::Unbounded),
+            line_start_cache,
+            data_start,
+            line_seeker: LineSeeker {
+                line: 0,
+                position: data_start,
+            },
+            layout: DataLayout::Lines,
+            registration: header.cell_registration(),
+            line: String::new(),
+        })
+    }
+    /// Sets how the values of the grid are laid out across the lines of the file.
+    #[must_use]
+    pub fn with_layout(mut self, layout: DataLayout) -> Self {
+        if layout != self.layout {
+            // Row positions and values cached under the old layout are no longer valid
+            self.line_cache.clear();
+            self.line_start_cache.fill(None);
+            if let Some(first) = self.line_start_cache.first_mut() {
+                *first = Some(self.data_start);
+            }
+            self.line_seeker.update(0, self.data_start);
+            self.layout = layout;
+        }
+        self
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Sets how many decoded rows are kept in memory. Any rows cached so far are dropped.
+    #[must_use]
+    pub fn with_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.line_cache = RowCache::new(self.header.num_rows(), policy);
+        self
+    }
+    /// Returns the hit, miss and eviction counts of the row cache, along with its current size.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.line_cache.stats()
+    }
+    /// Sets how interpolation treats the edges of the grid, overriding the registration given by the header.
+    ///
+    /// Behaves the same as `EsriASCIIReader::with_cell_registration`.
+    #[must_use]
+    pub fn with_cell_registration(mut self, registration: CellRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+    /// Returns how interpolation treats the edges of the grid.
+    pub fn cell_registration(&self) -> CellRegistration {
+        self.registration
+    }
+    /// Returns the value at the given row and column.
+    /// 0, 0 is the top left corner. The row and column are zero indexed.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    ///
+    /// # Panics
+    /// Panics if a cached row is missing right after being found in the cache, which should not happen.
+    pub async fn get_index(&mut self, row: usize, col: usize) -> Result<U, Error> {
+        if row >= self.header.nrows || col >= self.header.ncols {
+            Err(Error::OutOfBounds(row, col))?;
+        }
+        if self.line_cache.touch(row) {
+            return Ok(self.line_cache.get(row).unwrap()[col]);
+        }
+        let values = self.read_row(row).await?;
+        Ok(self.line_cache.insert(row, values)[col])
+    }
+    /// Returns the value at the given row and column, or nothing if the cell is nodata.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    pub async fn get_index_masked(&mut self, row: usize, col: usize) -> Result<Option<U>, Error> {
+        let value = self.get_index(row, col).await?;
+        Ok(self.header.mask(value))
+    }
+    /// Returns the value of the cell containing the given x and y coordinates, or nothing if they are out of bounds.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get`.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub async fn get(&mut self, x: T, y: T) -> Option<U> {
+        let (row, col) = self.header.index_of(x, y)?;
+        Some(self.get_index(row, col).await.unwrap())
+    }
+    /// Returns the value at the given x and y coordinates, or nothing if they are out of bounds or the cell is nodata.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub async fn get_masked(&mut self, x: T, y: T) -> Option<U> {
+        let value = self.get(x, y).await?;
+        self.header.mask(value)
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::async_reader::AsyncEsriASCIIReader;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let data = std::fs::read("test_data/test_llcorner.asc").unwrap();
+    /// let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+    ///     AsyncEsriASCIIReader::from_file(std::io::Cursor::new(data)).await.unwrap();
+    /// // Halfway between the centres of two cells
+    /// assert_eq!(grid.get_interpolate(125.0, 150.0).await, Some(42.5));
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if a contributing cell cannot be read. Use `get_interpolate_with` to handle the error instead.
+    pub async fn get_interpolate(&mut self, x: T, y: T) -> Option<U> {
+        self.get_interpolate_with(x, y, NodataPolicy::default())
+            .await
+            .unwrap()
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate_with`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub async fn get_interpolate_with(
+        &mut self,
+        x: T,
+        y: T,
+        policy: NodataPolicy,
+    ) -> Result<Option<U>, Error> {
+        let rows = self.rows_needed(x, y, Interpolation::Bilinear).await?;
+        interpolate::bilinear(&self.header, self.registration, x, y, policy, |row, col| {
+            get_from_rows(&rows, row, col)
+        })
+    }
+    /// Returns the value at the given x and y coordinates, sampled from the nearby cells using `method`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::sample`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub async fn sample(&mut self, x: T, y: T, method: Interpolation) -> Result<Option<U>, Error> {
+        let rows = self.rows_needed(x, y, method).await?;
+        interpolate::sample(
+            &self.header,
+            self.registration,
+            x,
+            y,
+            method,
+            NodataPolicy::default(),
+            |row, col| get_from_rows(&rows, row, col),
+        )
+    }
+    /// Reads every row that sampling at `x` and `y` with `method` may use, so that they can be sampled without waiting on the file.
+    ///
+    /// The rows are read through the cache, as with `get_index`.
+    async fn rows_needed(
+        &mut self,
+        x: T,
+        y: T,
+        method: Interpolation,
+    ) -> Result<BTreeMap<usize, Vec<U>>, Error> {
+        let mut rows = BTreeMap::new();
+        let needed = interpolate::rows_needed(&self.header, self.registration, x, y, method);
+        for row in needed.into_iter().flatten() {
+            let values = if self.line_cache.touch(row) {
+                self.line_cache.get(row).unwrap().to_vec()
+            } else {
+                let values = self.read_row(row).await?;
+                self.line_cache.insert(row, values).to_vec()
+            };
+            rows.insert(row, values);
+        }
+        Ok(rows)
+    }
+    /// Reads and parses the given row from the file, bypassing the cache.
+    async fn read_row(&mut self, row: usize) -> Result<Vec<U>, Error> {
+        let values = self.read_row_from_position(row).await;
+        if values.is_err() {
+            // The reader may have stopped partway through a row, without the bytes it read being counted
+            self.position = UNKNOWN_POSITION;
+        }
+        values
+    }
+    /// Reads and parses the given row, assuming that `position` is where the reader is.
+    async fn read_row_from_position(&mut self, row: usize) -> Result<Vec<U>, Error> {
+        let (layout, ncols) = (self.layout, self.header.ncols);
+        if let Some(line_pos) = self.line_start_cache[row] {
+            self.seek_to(line_pos).await?;
+        } else {
+            // Scan forward from the furthest row found so far, recording where each row starts
+            let (latest_line, latest_pos) = (self.line_seeker.line, self.line_seeker.position);
+            self.seek_to(latest_pos).await?;
+            for line in latest_line..row {
+                self.line_start_cache[line] = Some(self.position);
+                let (found, consumed) =
+                    read_row_text(&mut self.reader, layout, ncols, &mut self.line).await?;
+                if !found {
+                    Err(Error::MismatchedRowCount(row, line))?;
+                }
+                self.position += consumed;
+            }
+            self.line_start_cache[row] = Some(self.position);
+            self.line_seeker.update(row, self.position);
+        }
+        let (values, consumed) =
+            read_next_row(&mut self.reader, layout, row, ncols, &mut self.line).await?;
+        self.position += consumed;
+        let values = values.ok_or(Error::MismatchedRowCount(self.header.nrows, row))?;
+        // Remember where the next row starts, so reading the grid in order never scans a row twice
+        let next_row = row + 1;
+        if next_row < self.header.nrows && self.line_start_cache[next_row].is_none() {
+            self.line_start_cache[next_row] = Some(self.position);
+            if self.line_seeker.line < next_row {
+                self.line_seeker.update(next_row, self.position);
+            }
+        }
+        Ok(values)
+    }
+    /// Seeks to an absolute position, unless the reader is already there, which keeps its buffer.
+    async fn seek_to(&mut self, position: u64) -> Result<(), Error> {
+        if position != self.position {
+            self.reader.seek(SeekFrom::Start(position)).await?;
+            self.position = position;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value at the given row and column from rows read ahead of sampling.
+fn get_from_rows<U: Copy>(
+    rows: &BTreeMap<usize, Vec<U>>,
+    row: usize,
+    col: usize,
+) -> Result<U, Error> {
+    rows.get(&row)
+        .and_then(|values| values.get(col))
+        .copied()
+        .ok_or(Error::OutOfBounds(row, col))
+}
+
+/// Stands in for the position of a reader that has stopped at an unknown position, which no seek can match.
+const UNKNOWN_POSITION: u64 = u64::MAX;
+
+/// Reads the values of the next row, or nothing if there is no more data, along with the number of bytes read.
+///
+/// `line` is a scratch buffer that is reused between calls.
+async fn read_next_row<B, U>(
+    reader: &mut B,
+    layout: DataLayout,
+    row: usize,
+    ncols: usize,
+    line: &mut String,
+) -> Result<(Option<Vec<U>>, u64), Error>
+where
+    B: AsyncBufRead + Unpin,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    let (found, consumed) = read_row_text(reader, layout, ncols, line).await?;
+    if !found {
+        return Ok((None, consumed));
+    }
+    Ok((Some(parse_row(line, row, ncols)?), consumed))
+}
+/// Reads the text of the next row into `line` without parsing it, returning false if there is no more data,
+/// along with the number of bytes read.
+async fn read_row_text<B: AsyncBufRead + Unpin>(
+    reader: &mut B,
+    layout: DataLayout,
+    ncols: usize,
+    line: &mut String,
+) -> Result<(bool, u64), Error> {
+    let mut tokenizer = RowTokenizer::new(layout, ncols, line);
+    while !tokenizer.is_complete() {
+        let taken = tokenizer.feed(reader.fill_buf().await?);
+        reader.consume(taken);
+    }
+    let consumed = tokenizer.consumed();
+    Ok((tokenizer.finish(line)?, consumed))
+}
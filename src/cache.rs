@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, mem::size_of_val};
+
+/// How many decoded rows a reader keeps in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Every row that is read is kept for the lifetime of the reader.
+    #[default]
+    Unbounded,
+    /// At most this many rows are kept, evicting the least recently used row first.
+    MaxRows(usize),
+    /// At most this many bytes of values are kept, evicting the least recently used row first.
+    MaxBytes(usize),
+    /// No rows are kept, so every access parses its row again.
+    Disabled,
+}
+
+/// Counters describing how well the row cache is working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// The number of row lookups that were served from the cache.
+    pub hits: u64,
+    /// The number of row lookups that had to read the row from the file.
+    pub misses: u64,
+    /// The number of rows removed from the cache to stay within its limit.
+    pub evictions: u64,
+    /// The number of rows currently cached.
+    pub rows: usize,
+    /// The number of bytes of values currently cached.
+    pub bytes: usize,
+}
+
+/// A cache of decoded rows, indexed by row number, with least recently used eviction.
+#[derive(Debug)]
+pub(crate) struct RowCache<U> {
+    policy: CachePolicy,
+    /// The values of each cached row, and the tick it was last used at.
+    rows: Vec<Option<(Vec<U>, u64)>>,
+    /// Cached rows ordered by the tick they were last used at, oldest first.
+    recency: BTreeMap<u64, usize>,
+    tick: u64,
+    /// Holds the latest row when it could not be cached, so that it can still be borrowed.
+    uncached: Vec<U>,
+    stats: CacheStats,
+}
+impl<U: Clone> RowCache<U> {
+    pub(crate) fn new(nrows: usize, policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            rows: vec![None; nrows],
+            recency: BTreeMap::new(),
+            tick: 0,
+            uncached: Vec::new(),
+            stats: CacheStats::default(),
+        }
+    }
+    pub(crate) fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.stats
+    }
+    /// Returns the cached values of a row without counting a hit or marking it as used.
+    pub(crate) fn get(&self, row: usize) -> Option<&[U]> {
+        self.rows[row].as_ref().map(|(values, _)| values.as_slice())
+    }
+    /// Looks up a row, counting a hit or a miss. On a hit the row becomes the most recently used.
+    pub(crate) fn touch(&mut self, row: usize) -> bool {
+        let tick = self.next_tick();
+        let Some((_, last_used)) = &mut self.rows[row] else {
+            self.stats.misses += 1;
+            return false;
+        };
+        self.recency.remove(last_used);
+        self.recency.insert(tick, row);
+        *last_used = tick;
+        self.stats.hits += 1;
+        true
+    }
+    /// Caches the values of a row, evicting other rows if needed, and returns them.
+    ///
+    /// If the policy does not allow the row to be cached at all, it is held only until the next insert.
+    pub(crate) fn insert(&mut self, row: usize, values: Vec<U>) -> &[U] {
+        let size = size_of_val(values.as_slice());
+        let fits = match self.policy {
+            CachePolicy::Unbounded => true,
+            CachePolicy::MaxRows(max_rows) => max_rows > 0,
+            CachePolicy::MaxBytes(max_bytes) => size <= max_bytes,
+            CachePolicy::Disabled => false,
+        };
+        if !fits {
+            self.uncached = values;
+            return &self.uncached;
+        }
+        self.take(row);
+        while !self.has_room_for(size) {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.take(oldest);
+            self.stats.evictions += 1;
+        }
+        let tick = self.next_tick();
+        self.recency.insert(tick, row);
+        self.stats.rows += 1;
+        self.stats.bytes += size;
+        &self.rows[row].insert((values, tick)).0
+    }
+    /// Removes every cached row, keeping the counters.
+    pub(crate) fn clear(&mut self) {
+        self.rows.fill(None);
+        self.recency.clear();
+        self.uncached = Vec::new();
+        self.stats.rows = 0;
+        self.stats.bytes = 0;
+    }
+    fn has_room_for(&self, size: usize) -> bool {
+        match self.policy {
+            CachePolicy::Unbounded => true,
+            CachePolicy::MaxRows(max_rows) => self.stats.rows < max_rows,
+            CachePolicy::MaxBytes(max_bytes) => self.stats.bytes + size <= max_bytes,
+            CachePolicy::Disabled => false,
+        }
+    }
+    /// Removes a row from the cache and returns its values, without counting a hit or a miss.
+    pub(crate) fn take(&mut self, row: usize) -> Option<Vec<U>> {
+        let (values, last_used) = self.rows[row].take()?;
+        self.recency.remove(&last_used);
+        self.stats.rows -= 1;
+        self.stats.bytes -= size_of_val(values.as_slice());
+        Some(values)
+    }
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read},
+    path::Path,
+};
+
+use crate::{
+    ascii_file::EsriASCIIReader,
+    error::{self, Error},
+    header::Numerical,
+    stream::EsriASCIIStreamReader,
+};
+
+/// The compression format of a grid file.
+///
+/// Only available with the `gzip` or `zstd` features, and only the formats whose feature is enabled can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// The file is not compressed.
+    None,
+    /// The file is compressed with gzip, as in `.asc.gz` files.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// The file is compressed with zstd, as in `.asc.zst` files.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+impl Compression {
+    /// Detects the compression format from the first bytes of a file, which is `Compression::None` for anything not recognised.
+    #[must_use]
+    pub fn detect(magic: &[u8]) -> Self {
+        #[cfg(feature = "gzip")]
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            return Self::Gzip;
+        }
+        #[cfg(feature = "zstd")]
+        if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Self::Zstd;
+        }
+        Self::None
+    }
+    /// Wraps `reader` in a decoder for this format.
+    ///
+    /// # Errors
+    /// Returns an error if the decoder cannot be created.
+    pub fn decoder<'a, B>(self, reader: B) -> Result<Box<dyn Read + Send + 'a>, Error>
+    where
+        B: BufRead + Send + 'a,
+    {
+        Ok(match self {
+            Self::None => Box::new(reader),
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        })
+    }
+}
+
+/// Opens the file at `path`, decompressing it if its first bytes show it is compressed.
+fn open_decompressed(path: &Path) -> Result<Box<dyn Read + Send>, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(reader.fill_buf()?);
+    compression.decoder(reader)
+}
+
+impl<T, U> EsriASCIIStreamReader<BufReader<Box<dyn Read + Send>>, T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Opens a grid that may be compressed, decompressing it as it is read.
+    ///
+    /// The compression format is detected from the content of the file, not its extension, and uncompressed files are read as they are.
+    /// Nothing is buffered beyond the row being read, so this is the way to read every cell of a large compressed grid.
+    ///
+    /// Only available with the `gzip` or `zstd` features.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened, or there is something wrong with the header.
+    pub fn from_compressed_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::new(BufReader::new(open_decompressed(path.as_ref())?))
+    }
+}
+impl<T, U> EsriASCIIReader<Cursor<Vec<u8>>, T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Opens a grid that may be compressed for random access, by decompressing the whole file into memory.
+    ///
+    /// The compression format is detected from the content of the file, not its extension, and uncompressed files are read as they are.
+    /// Compressed streams cannot seek, so the decompressed text of the grid is kept in memory for the lifetime of the reader.
+    /// Use `EsriASCIIStreamReader::from_compressed_file` to read every cell in order without buffering the file.
+    ///
+    /// Only available with the `gzip` or `zstd` features.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened or decompressed, or there is something wrong with the header.
+    pub fn from_compressed_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        open_decompressed(path.as_ref())?.read_to_end(&mut data)?;
+        Self::from_file(Cursor::new(data))
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     io,
     num::{ParseFloatError, ParseIntError},
+    ops::Range,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -14,6 +15,12 @@ pub enum Error {
     #[error("Expecting {0}; got {1}")]
     MismatchedField(String, String),
 
+    #[error("Field {0} appears more than once")]
+    DuplicateField(String),
+
+    #[error("Field {0} is not a known header field")]
+    UnknownField(String),
+
     #[error("Field {0} expects a value but it's missing")]
     MissingValue(String),
 
@@ -39,9 +46,32 @@ pub enum Error {
     #[error("Expecting {0} columns; got {1}")]
     MismatchColumnCount(usize, usize),
 
+    #[error("Expecting {0} cells; got {1}")]
+    MismatchedCellCount(usize, usize),
+
     #[error("The given index ({0}, {1}) is out of bounds")]
     OutOfBounds(usize, usize),
 
+    #[error("The window of rows {0:?} and columns {1:?} is empty")]
+    EmptyWindow(Range<usize>, Range<usize>),
+
+    #[error("The extent from {0:?} in x and {1:?} in y does not overlap the grid")]
+    ExtentOutOfBounds(Range<f64>, Range<f64>),
+
+    #[error("The extent from {0:?} in x and {1:?} in y has a minimum greater than its maximum")]
+    InvertedExtent(Range<f64>, Range<f64>),
+
+    #[error("The extent from {0:?} in x and {1:?} in y cannot be placed on the cells of the grid")]
+    InvalidExtent(Range<f64>, Range<f64>),
+
+    #[error(
+        "The window of rows {0:?} and columns {1:?} extends past the grid, which has no nodata value to pad it with"
+    )]
+    PaddingWithoutNodata(Range<isize>, Range<isize>),
+
+    #[error("The row index is invalid: {0}")]
+    InvalidIndex(String),
+
     #[error("The value {0} in {1} cannot be represented as type {2}")]
     TypeCast(String, String, &'static str),
 }
@@ -0,0 +1,24 @@
+use std::any::Any;
+
+/// Parses a grid value with lexical-core, which is much faster than `FromStr` for primitive numbers.
+///
+/// Returns nothing if `U` is not a primitive number, or if lexical-core rejects the token, so that the caller can fall
+/// back to `FromStr`. Tokens that both accept are parsed to exactly the same value.
+pub(crate) fn parse<U: 'static>(token: &str) -> Option<U> {
+    let mut value: Option<U> = None;
+    let slot: &mut dyn Any = &mut value;
+    macro_rules! parse_as {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(slot) = slot.downcast_mut::<Option<$ty>>() {
+                    *slot = lexical_core::parse::<$ty>(token.as_bytes()).ok();
+                    return value;
+                }
+            )*
+        };
+    }
+    parse_as!(
+        f64, f32, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+    );
+    None
+}
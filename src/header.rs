@@ -1,8 +1,12 @@
-use crate::error::{self, Error};
+use crate::{
+    error::{self, Error},
+    interpolate::CellRegistration,
+};
 use num_traits::{Num, NumAssign, NumAssignOps, NumAssignRef, NumCast, NumRef};
 use std::{
     fmt::Debug,
-    io::{self, BufRead, BufReader, Read, Seek},
+    io::{BufRead, BufReader, Read, Seek},
+    ops::Range,
     str::FromStr,
 };
 
@@ -21,6 +25,7 @@ pub trait Numerical:
     + Clone
     + Copy
     + Debug
+    + FastParseBound
 {
     type Err: Debug;
 }
@@ -38,12 +43,29 @@ where
         + PartialEq
         + Clone
         + Copy
-        + Debug,
+        + Debug
+        + FastParseBound,
     <T as FromStr>::Err: Debug,
     error::Error: From<<T as FromStr>::Err>,
 {
     type Err = <T as FromStr>::Err;
 }
+/// The extra bound that the `fast-parse` feature puts on `Numerical` types.
+///
+/// With the feature, values are parsed by looking up their type at runtime, which needs the type to be `'static`.
+/// Without it, every type meets this bound.
+#[cfg(feature = "fast-parse")]
+pub trait FastParseBound: 'static {}
+#[cfg(feature = "fast-parse")]
+impl<T: 'static> FastParseBound for T {}
+/// The extra bound that the `fast-parse` feature puts on `Numerical` types.
+///
+/// With the feature, values are parsed by looking up their type at runtime, which needs the type to be `'static`.
+/// Without it, every type meets this bound.
+#[cfg(not(feature = "fast-parse"))]
+pub trait FastParseBound {}
+#[cfg(not(feature = "fast-parse"))]
+impl<T> FastParseBound for T {}
 
 /// A reader for ESRI ASCII raster files.
 /// This reader reads the header of the file and then reads the data on demand.
@@ -66,6 +88,12 @@ where
     pub yur: T,
     pub xur: T,
     pub cornertype: CornerType,
+    /// The width of a cell.
+    pub dx: T,
+    /// The height of a cell.
+    pub dy: T,
+    /// The width of a cell, the same as `dx`.
+    #[deprecated(note = "use `dx` and `dy`, which also support non-square cells")]
     pub cellsize: T,
     pub nodata_value: Option<U>,
 }
@@ -76,26 +104,52 @@ where
     U: Numerical,
     error::Error: From<<U as Numerical>::Err>,
 {
-    /// Creates a new `EsriASCIIRasterHeader` instance.
+    /// Creates a new `EsriASCIIRasterHeader` instance with square cells.
     ///
     /// # Panics
     /// Panics if type T does not support `T::from(i32)`
     pub fn new(
+        ncols: usize,
+        nrows: usize,
+        xll: T,
+        yll: T,
+        cornertype: CornerType,
+        cellsize: T,
+        nodata_value: Option<U>,
+    ) -> Self {
+        Self::new_with_cell_sizes(
+            ncols,
+            nrows,
+            xll,
+            yll,
+            cornertype,
+            cellsize,
+            cellsize,
+            nodata_value,
+        )
+    }
+    /// Creates a new `EsriASCIIRasterHeader` instance with cells that are `dx` wide and `dy` high.
+    ///
+    /// # Panics
+    /// Panics if type T does not support `T::from(i32)`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_cell_sizes(
         ncols: usize,
         nrows: usize,
         mut xll: T,
         mut yll: T,
         cornertype: CornerType,
-        cellsize: T,
+        dx: T,
+        dy: T,
         nodata_value: Option<U>,
     ) -> Self {
         let two: T = T::from(2).unwrap();
         if cornertype == CornerType::Center {
-            xll -= cellsize / two;
-            yll -= cellsize / two;
+            xll -= dx / two;
+            yll -= dy / two;
         }
-        let xur = xll + cellsize * T::from(ncols).unwrap();
-        let yur = yll + cellsize * T::from(nrows).unwrap();
+        let xur = xll + dx * T::from(ncols).unwrap();
+        let yur = yll + dy * T::from(nrows).unwrap();
 
         Self {
             ncols,
@@ -105,7 +159,10 @@ where
             yur,
             xur,
             cornertype,
-            cellsize,
+            dx,
+            dy,
+            #[allow(deprecated)]
+            cellsize: dx,
             nodata_value,
         }
     }
@@ -113,29 +170,76 @@ where
         reader: &mut BufReader<R>,
     ) -> Result<EsriASCIIRasterHeader<T, U>, Error> {
         reader.rewind()?;
-        let mut lines = reader.lines();
-
-        let ncols = parse_header_line::<usize>(lines.next(), "ncols")?;
-        let nrows = parse_header_line::<usize>(lines.next(), "nrows")?;
-
-        let (corner_type_x, xll) = parse_ll(lines.next(), "xll")?;
-        let (corner_type_y, yll) = parse_ll(lines.next(), "yll")?;
-        if corner_type_x != corner_type_y {
-            Err(Error::BrokenInvariant("corner type disagree".into()))?;
+        Self::from_buf_read(reader)
+    }
+    /// Reads header lines from the current position until the first line that does not start with a header key.
+    ///
+    /// The keys may appear in any order and are case-insensitive, and the `NODATA_value` line is optional.
+    /// The data section is detected by peeking at the buffer, so when this returns the reader is positioned at the first value of the grid.
+    ///
+    /// # Errors
+    /// Returns an error if a header line cannot be read or parsed, a key is repeated or unknown, or a required key is missing.
+    pub fn from_buf_read<B: BufRead>(reader: &mut B) -> Result<EsriASCIIRasterHeader<T, U>, Error> {
+        let mut parser = HeaderParser::default();
+        let mut line = String::new();
+        loop {
+            // Skip blank lines and indentation, so that the next byte is the start of a key or value
+            let buf = reader.fill_buf()?;
+            let whitespace = buf.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            let at_end = whitespace == buf.len();
+            reader.consume(whitespace);
+            if at_end {
+                if whitespace == 0 {
+                    // End of the file
+                    break;
+                }
+                continue;
+            }
+            if !starts_with_key(reader.fill_buf()?) {
+                break;
+            }
+            line.clear();
+            reader.read_line(&mut line)?;
+            parser.parse_line(&line)?;
         }
+        parser.finish()
+    }
+    /// Reads header lines from an async reader, in the same way as `from_buf_read`.
+    ///
+    /// Only available with the `tokio` feature.
+    ///
+    /// # Errors
+    /// Returns an error if a header line cannot be read or parsed, a key is repeated or unknown, or a required key is missing.
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_buf_read<B>(
+        reader: &mut B,
+    ) -> Result<EsriASCIIRasterHeader<T, U>, Error>
+    where
+        B: tokio::io::AsyncBufRead + Unpin,
+    {
+        use tokio::io::AsyncBufReadExt;
 
-        let cellsize = parse_header_line(lines.next(), "cellsize")?;
-        let nodata_value = parse_header_line(lines.next(), "nodata_value").ok();
-
-        Ok(Self::new(
-            ncols,
-            nrows,
-            xll,
-            yll,
-            corner_type_x,
-            cellsize,
-            nodata_value,
-        ))
+        let mut parser = HeaderParser::default();
+        let mut line = String::new();
+        loop {
+            let buf = reader.fill_buf().await?;
+            let whitespace = buf.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            let at_end = whitespace == buf.len();
+            reader.consume(whitespace);
+            if at_end {
+                if whitespace == 0 {
+                    break;
+                }
+                continue;
+            }
+            if !starts_with_key(reader.fill_buf().await?) {
+                break;
+            }
+            line.clear();
+            reader.read_line(&mut line).await?;
+            parser.parse_line(&line)?;
+        }
+        parser.finish()
     }
     pub fn num_rows(&self) -> usize {
         self.nrows
@@ -155,12 +259,37 @@ where
     pub fn max_y(&self) -> T {
         self.yur
     }
+    /// The width of a cell, which is also its height for grids with square cells.
+    #[deprecated(note = "use `cell_size_x` and `cell_size_y`, which also support non-square cells")]
     pub fn cell_size(&self) -> T {
-        self.cellsize
+        self.dx
+    }
+    /// The width of a cell, given by `cellsize` or `dx` in the header.
+    pub fn cell_size_x(&self) -> T {
+        self.dx
+    }
+    /// The height of a cell, given by `cellsize` or `dy` in the header.
+    pub fn cell_size_y(&self) -> T {
+        self.dy
     }
     pub fn no_data_value(&self) -> Option<U> {
         self.nodata_value
     }
+    /// Returns true if `value` is the nodata value of the grid.
+    ///
+    /// A NaN nodata value matches any NaN value.
+    pub fn is_nodata(&self, value: U) -> bool {
+        self.nodata_value
+            .is_some_and(|nodata| is_same_value(nodata, value))
+    }
+    /// Returns the value, or nothing if it is the nodata value of the grid.
+    pub fn mask(&self, value: U) -> Option<U> {
+        if self.is_nodata(value) {
+            None
+        } else {
+            Some(value)
+        }
+    }
     /// ESRI ASCII files can have either a corner or center cell type.
     ///
     /// If the cell type is corner, the values are the at coordinates of the bottom left corner of the cell.
@@ -169,6 +298,16 @@ where
     pub fn corner_type(&self) -> CornerType {
         self.cornertype
     }
+    /// How the values of the grid relate to the area of their cells, as suggested by the header.
+    ///
+    /// A grid positioned by the centre of its lower left cell (`xllcenter`) is taken to hold point samples, `CellRegistration::PixelIsPoint`,
+    /// and one positioned by its lower left corner (`xllcorner`) to hold cell averages, `CellRegistration::PixelIsArea`.
+    pub fn cell_registration(&self) -> CellRegistration {
+        match self.cornertype {
+            CornerType::Corner => CellRegistration::PixelIsArea,
+            CornerType::Center => CellRegistration::PixelIsPoint,
+        }
+    }
     /// Get the x and y coordinates of the cell at the given row and column, or nothing if it is out of bounds.
     ///
     /// # Panics
@@ -179,8 +318,8 @@ where
         if row >= nrows || col >= ncols {
             return None;
         }
-        let x = self.min_x() + self.cell_size() * T::from(col).unwrap();
-        let y = self.max_y() - self.cell_size() * T::from(row).unwrap() - self.cell_size();
+        let x = self.min_x() + self.cell_size_x() * T::from(col).unwrap();
+        let y = self.max_y() - self.cell_size_y() * T::from(row).unwrap() - self.cell_size_y();
         Some((x, y))
     }
     /// Get the row and column index of the cell that contains the given x and y, or nothing if it is out of bounds.
@@ -197,8 +336,8 @@ where
         }
         let dist_x = x - min_x;
         let dist_y = y - min_y;
-        let mut index_x = dist_x / self.cell_size();
-        let mut index_y = dist_y / self.cell_size();
+        let mut index_x = dist_x / self.cell_size_x();
+        let mut index_y = dist_y / self.cell_size_y();
         let one: T = T::from(1).unwrap();
         if x == max_x {
             index_x -= one;
@@ -213,6 +352,84 @@ where
 
         Some((row, col))
     }
+    /// Get the header of the sub-grid made up of the given rows and columns, or nothing if they are empty or out of bounds.
+    ///
+    /// The corner type and cell sizes are kept, and the extent is moved to the cells of the window.
+    ///
+    /// # Panics
+    /// Panics if `T::from` fails to convert the row or column indices into the type `T`.
+    pub fn window(&self, rows: Range<usize>, cols: Range<usize>) -> Option<Self> {
+        if rows.is_empty() || cols.is_empty() || rows.end > self.nrows || cols.end > self.ncols {
+            return None;
+        }
+        let xll = self.xll + self.dx * T::from(cols.start).unwrap();
+        let yur = self.yur - self.dy * T::from(rows.start).unwrap();
+        Some(Self {
+            ncols: cols.len(),
+            nrows: rows.len(),
+            xll,
+            yll: self.yur - self.dy * T::from(rows.end).unwrap(),
+            xur: xll + self.dx * T::from(cols.len()).unwrap(),
+            yur,
+            ..*self
+        })
+    }
+    /// Get the rows and columns of the cells that overlap the given extent, or nothing if it does not overlap the grid.
+    ///
+    /// The extent is clipped to the grid and snapped outwards to whole cells. Cells that only touch the extent along an edge
+    /// are not included, so an extent that only touches an edge of the grid does not overlap it. An extent with no width or
+    /// no height selects the cells it lies in, with a point on the edge between two cells in the cell to its right or above, as in `index_of`.
+    pub fn window_of(
+        &self,
+        min_x: T,
+        min_y: T,
+        max_x: T,
+        max_y: T,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let (rows, cols) = self.cells_of(min_x, min_y, max_x, max_y)?;
+        let clip = |cells: Range<f64>, len: usize| {
+            let cells = cells.start.max(0.0)..cells.end.min(len as f64);
+            (!cells.is_empty()).then_some(cells.start as usize..cells.end as usize)
+        };
+        Some((clip(rows, self.nrows)?, clip(cols, self.ncols)?))
+    }
+    /// Get the rows and columns of the cells that overlap the given extent, chosen as in `window_of` but on the grid extended
+    /// past its edges in every direction, so they can be negative or past the end of the grid.
+    ///
+    /// Nothing is returned if the minimum of the extent is greater than its maximum, or it is not finite.
+    pub(crate) fn cells_of(
+        &self,
+        min_x: T,
+        min_y: T,
+        max_x: T,
+        max_y: T,
+    ) -> Option<(Range<f64>, Range<f64>)> {
+        if min_x > max_x || min_y > max_y {
+            return None;
+        }
+        let to_f64 = |value: T| -> Option<f64> {
+            NumCast::from(value).filter(|value: &f64| value.is_finite())
+        };
+        let (left, top) = (to_f64(self.xll)?, to_f64(self.yur)?);
+        let (dx, dy) = (to_f64(self.dx)?, to_f64(self.dy)?);
+        let mut cols =
+            ((to_f64(min_x)? - left) / dx).floor()..((to_f64(max_x)? - left) / dx).ceil();
+        let mut rows = ((top - to_f64(max_y)?) / dy).floor()..((top - to_f64(min_y)?) / dy).ceil();
+        if ![cols.start, cols.end, rows.start, rows.end]
+            .iter()
+            .all(|cell| cell.is_finite())
+        {
+            return None;
+        }
+        // An extent with no width or height is in the cells to its right or above
+        if cols.is_empty() {
+            cols.end = cols.start + 1.0;
+        }
+        if rows.is_empty() {
+            rows.start = rows.end - 1.0;
+        }
+        Some((rows, cols))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -232,51 +449,155 @@ impl FromStr for CornerType {
     }
 }
 
-fn parse_header_line<T>(line: Option<Result<String, io::Error>>, expected: &str) -> Result<T, Error>
-where
-    T: FromStr,
-    Error: From<<T as FromStr>::Err>,
-{
-    let line = line.ok_or_else(|| Error::MissingField(expected.into()))??;
-    let mut tokens_it = line.split_whitespace();
+/// The keys that may appear in a header.
+const HEADER_KEYS: [&str; 10] = [
+    "ncols",
+    "nrows",
+    "xllcorner",
+    "xllcenter",
+    "yllcorner",
+    "yllcenter",
+    "cellsize",
+    "dx",
+    "dy",
+    "nodata_value",
+];
 
-    let field = tokens_it
-        .next()
-        .ok_or_else(|| Error::MissingField(expected.into()))?;
-    if field.to_lowercase() != expected {
-        Err(Error::MismatchedField(expected.into(), field.into()))?;
-    }
-    let val_str = tokens_it
-        .next()
-        .ok_or_else(|| Error::MissingValue(expected.into()))?;
-    let value: Result<T, _> = val_str
-        .parse()
-        .map_err(|_| Error::TypeCast(val_str.into(), field.into(), std::any::type_name::<T>()));
-    value
+/// Returns true if the buffered data starts with a header key rather than a grid value.
+///
+/// Keys start with a letter, but so do special float values such as `nan` and `inf`, which belong to the grid.
+pub(crate) fn starts_with_key(buf: &[u8]) -> bool {
+    let token_len = buf
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .unwrap_or(buf.len());
+    let token = String::from_utf8_lossy(&buf[..token_len]);
+    token.starts_with(|c: char| c.is_ascii_alphabetic()) && token.parse::<f64>().is_err()
+}
+
+/// Returns true if `a` and `b` are equal, treating every NaN as equal to every other NaN.
+#[allow(clippy::eq_op)]
+pub(crate) fn is_same_value<U: PartialEq + Copy>(a: U, b: U) -> bool {
+    a == b || (a != a && b != b)
 }
 
-fn parse_ll<T>(
-    line: Option<Result<String, io::Error>>,
-    expected_prefix: &str,
-) -> Result<(CornerType, T), Error>
+/// Accumulates the values of header lines, which can appear in any order.
+#[derive(Debug)]
+pub(crate) struct HeaderParser<T, U> {
+    ncols: Option<usize>,
+    nrows: Option<usize>,
+    xll: Option<(CornerType, T)>,
+    yll: Option<(CornerType, T)>,
+    cellsize: Option<T>,
+    dx: Option<T>,
+    dy: Option<T>,
+    nodata_value: Option<U>,
+}
+impl<T, U> Default for HeaderParser<T, U> {
+    fn default() -> Self {
+        Self {
+            ncols: None,
+            nrows: None,
+            xll: None,
+            yll: None,
+            cellsize: None,
+            dx: None,
+            dy: None,
+            nodata_value: None,
+        }
+    }
+}
+impl<T, U> HeaderParser<T, U>
 where
-    T: FromStr,
-    Error: From<<T as FromStr>::Err>,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
 {
-    let expected_prefix = format!("{expected_prefix}corner or {expected_prefix}center");
-    let line = line.ok_or_else(|| Error::MissingField(expected_prefix.clone()))??;
-    let mut tokens_it = line.split_whitespace();
+    /// Parses a single `key value` line of the header.
+    pub(crate) fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        let mut tokens_it = line.split_whitespace();
+        let Some(field) = tokens_it.next() else {
+            return Ok(());
+        };
+        let key = field.to_lowercase();
+        if !HEADER_KEYS.contains(&key.as_str()) {
+            Err(Error::UnknownField(field.into()))?;
+        }
+        let val_str = tokens_it
+            .next()
+            .ok_or_else(|| Error::MissingValue(field.into()))?;
+        match key.as_str() {
+            "ncols" => set_once(&mut self.ncols, field, parse_value(val_str, field)?),
+            "nrows" => set_once(&mut self.nrows, field, parse_value(val_str, field)?),
+            "xllcorner" | "xllcenter" => set_once(
+                &mut self.xll,
+                field,
+                (CornerType::from_str(&key)?, parse_value(val_str, field)?),
+            ),
+            "yllcorner" | "yllcenter" => set_once(
+                &mut self.yll,
+                field,
+                (CornerType::from_str(&key)?, parse_value(val_str, field)?),
+            ),
+            "cellsize" => set_once(&mut self.cellsize, field, parse_value(val_str, field)?),
+            "dx" => set_once(&mut self.dx, field, parse_value(val_str, field)?),
+            "dy" => set_once(&mut self.dy, field, parse_value(val_str, field)?),
+            "nodata_value" => set_once(&mut self.nodata_value, field, parse_value(val_str, field)?),
+            _ => unreachable!(),
+        }
+    }
+    /// Checks that every required key was present and builds the header.
+    pub(crate) fn finish(self) -> Result<EsriASCIIRasterHeader<T, U>, Error> {
+        let ncols = self
+            .ncols
+            .ok_or_else(|| Error::MissingField("ncols".into()))?;
+        let nrows = self
+            .nrows
+            .ok_or_else(|| Error::MissingField("nrows".into()))?;
+        let (corner_type_x, xll) = self
+            .xll
+            .ok_or_else(|| Error::MissingField("xllcorner or xllcenter".into()))?;
+        let (corner_type_y, yll) = self
+            .yll
+            .ok_or_else(|| Error::MissingField("yllcorner or yllcenter".into()))?;
+        if corner_type_x != corner_type_y {
+            Err(Error::BrokenInvariant("corner type disagree".into()))?;
+        }
+        // Square cells are given by `cellsize`, non-square cells by both `dx` and `dy`
+        let (dx, dy) = match (self.cellsize, self.dx, self.dy) {
+            (Some(cellsize), None, None) => (cellsize, cellsize),
+            (None, Some(dx), Some(dy)) => (dx, dy),
+            (None, None, None) => Err(Error::MissingField("cellsize or dx and dy".into()))?,
+            (None, Some(_), None) => Err(Error::MissingField("dy".into()))?,
+            (None, None, Some(_)) => Err(Error::MissingField("dx".into()))?,
+            (Some(_), _, _) => Err(Error::BrokenInvariant(
+                "cellsize cannot be combined with dx or dy".into(),
+            ))?,
+        };
+        Ok(EsriASCIIRasterHeader::new_with_cell_sizes(
+            ncols,
+            nrows,
+            xll,
+            yll,
+            corner_type_x,
+            dx,
+            dy,
+            self.nodata_value,
+        ))
+    }
+}
 
-    let field = tokens_it
-        .next()
-        .ok_or_else(|| Error::MissingField(expected_prefix.clone()))?;
-    let corner_type = CornerType::from_str(field)?;
+fn set_once<V>(slot: &mut Option<V>, field: &str, value: V) -> Result<(), Error> {
+    if slot.is_some() {
+        Err(Error::DuplicateField(field.into()))?;
+    }
+    *slot = Some(value);
+    Ok(())
+}
 
-    let value_str = tokens_it
-        .next()
-        .ok_or_else(|| Error::MissingValue(expected_prefix.clone()))?;
-    let value = value_str
+fn parse_value<T: FromStr>(val_str: &str, field: &str) -> Result<T, Error> {
+    val_str
         .parse()
-        .map_err(|_| Error::TypeCast(value_str.into(), field.into(), std::any::type_name::<T>()))?;
-    Ok((corner_type, value))
+        .map_err(|_| Error::TypeCast(val_str.into(), field.into(), std::any::type_name::<T>()))
 }
@@ -0,0 +1,167 @@
+use std::{
+    fs::{File, Metadata},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    ascii_file::{DataLayout, EsriASCIIReader},
+    error::{self, Error},
+    header::Numerical,
+};
+
+/// Identifies a row index sidecar file, and the version of its format.
+const MAGIC: &[u8; 8] = b"ASCIDX01";
+
+/// The byte offset of the start of every row of a grid, which can be saved next to the grid in a sidecar file.
+///
+/// Loading the offsets lets `EsriASCIIReader::from_file_with_index` seek straight to any row,
+/// rather than scanning the file to find it. The size and modification time of the grid are stored
+/// alongside the offsets, so that an index for a grid that has since changed is not used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowIndex {
+    /// The layout the offsets were found with.
+    pub layout: DataLayout,
+    /// The size of the grid file, in bytes.
+    pub source_len: u64,
+    /// The modification time of the grid file, in nanoseconds since the Unix epoch, or 0 if it is not available.
+    pub source_modified: u128,
+    /// The byte offset of the start of every row.
+    pub offsets: Vec<u64>,
+}
+impl RowIndex {
+    /// Returns the path of the sidecar file for a grid, which is the grid's path with `.idx` appended, e.g. `dem.asc.idx`.
+    pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut sidecar = path.as_ref().as_os_str().to_owned();
+        sidecar.push(".idx");
+        PathBuf::from(sidecar)
+    }
+    /// Scans the grid at `path` to find the start of every row.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::DataLayout;
+    /// use esri_ascii_grid::index::RowIndex;
+    /// let index = RowIndex::build::<f64, f64>("test_data/test_llcorner.asc", DataLayout::Lines).unwrap();
+    /// assert_eq!(index.offsets.len(), 6);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the grid cannot be read, or has fewer rows than its header says.
+    pub fn build<T, U>(path: impl AsRef<Path>, layout: DataLayout) -> Result<Self, Error>
+    where
+        T: Numerical,
+        error::Error: From<<T as Numerical>::Err>,
+        U: Numerical,
+        error::Error: From<<U as Numerical>::Err>,
+    {
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let mut reader: EsriASCIIReader<File, T, U> =
+            EsriASCIIReader::from_file(file)?.with_layout(layout);
+        let offsets = reader.row_offsets()?;
+        Ok(Self {
+            layout,
+            source_len: metadata.len(),
+            source_modified: modified_nanos(&metadata),
+            offsets,
+        })
+    }
+    /// Builds the index for the grid at `path` and writes it to the grid's sidecar file.
+    ///
+    /// # Errors
+    /// Returns an error if the grid cannot be read or the sidecar file cannot be written.
+    pub fn build_sidecar<T, U>(path: impl AsRef<Path>, layout: DataLayout) -> Result<Self, Error>
+    where
+        T: Numerical,
+        error::Error: From<<T as Numerical>::Err>,
+        U: Numerical,
+        error::Error: From<<U as Numerical>::Err>,
+    {
+        let index = Self::build::<T, U>(&path, layout)?;
+        index.write(File::create(Self::sidecar_path(&path))?)?;
+        Ok(index)
+    }
+    /// Returns true if this index was built from a file with the given metadata.
+    #[must_use]
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        self.source_len == metadata.len() && self.source_modified == modified_nanos(metadata)
+    }
+    /// Writes the index in its binary sidecar format.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying writer fails.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[match self.layout {
+            DataLayout::Lines => 0,
+            DataLayout::Stream => 1,
+        }])?;
+        writer.write_all(&self.source_len.to_le_bytes())?;
+        writer.write_all(&self.source_modified.to_le_bytes())?;
+        writer.write_all(&(self.offsets.len() as u64).to_le_bytes())?;
+        for offset in &self.offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    /// Reads an index in its binary sidecar format.
+    ///
+    /// # Errors
+    /// Returns an error if reading fails or the data is not a row index,
+    /// including if its offsets do not strictly increase or do not all lie within the grid file.
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            Err(Error::InvalidIndex("not a row index file".into()))?;
+        }
+        let mut layout = [0; 1];
+        reader.read_exact(&mut layout)?;
+        let layout = match layout[0] {
+            0 => DataLayout::Lines,
+            1 => DataLayout::Stream,
+            other => Err(Error::InvalidIndex(format!("unknown layout {other}")))?,
+        };
+        let source_len = u64::from_le_bytes(read_array(&mut reader)?);
+        let source_modified = u128::from_le_bytes(read_array(&mut reader)?);
+        let nrows = u64::from_le_bytes(read_array(&mut reader)?);
+        let nrows = usize::try_from(nrows)
+            .map_err(|_| Error::InvalidIndex(format!("{nrows} rows is too many")))?;
+        let offsets = (0..nrows)
+            .map(|_| read_array(&mut reader).map(u64::from_le_bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        if offsets.windows(2).any(|pair| pair[0] >= pair[1]) {
+            Err(Error::InvalidIndex("row offsets do not increase".into()))?;
+        }
+        if offsets.last().is_some_and(|&last| last >= source_len) {
+            Err(Error::InvalidIndex(
+                "row offsets are past the end of the grid".into(),
+            ))?;
+        }
+        Ok(Self {
+            layout,
+            source_len,
+            source_modified,
+            offsets,
+        })
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn modified_nanos(metadata: &Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_nanos())
+}
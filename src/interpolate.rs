@@ -0,0 +1,367 @@
+use std::ops::RangeInclusive;
+
+use num_traits::NumCast;
+
+use crate::{
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+};
+
+/// What interpolation does when one of the cells it would blend is nodata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodataPolicy {
+    /// Nodata cells are left out, and the weights of the remaining cells are scaled up to compensate.
+    /// Nothing is returned if every cell that contributes to the result is nodata.
+    #[default]
+    Skip,
+    /// Nothing is returned if any cell that contributes to the result is nodata.
+    Refuse,
+}
+
+/// How the value of a cell relates to the area it covers, which decides how interpolation treats the edges of a grid.
+///
+/// Either way, interpolation places the value of each cell at its centre, and blends between the centres of neighbouring cells.
+/// Readers take it from the header, as given by `EsriASCIIRasterHeader::cell_registration`: grids positioned with `xllcenter`
+/// are `PixelIsPoint` and grids positioned with `xllcorner` are `PixelIsArea`. It can be overridden, e.g. with
+/// `EsriASCIIReader::with_cell_registration`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellRegistration {
+    /// Each value describes the whole of its cell, so the grid can be sampled anywhere within its extent.
+    /// Between the centres of the outermost cells and the edge of the grid, the value of the outermost cell is used.
+    #[default]
+    PixelIsArea,
+    /// Each value is a sample taken at the centre of its cell, so the grid can only be sampled between the centres of the
+    /// outermost cells. Interpolating nearer to the edge of the grid returns nothing.
+    PixelIsPoint,
+}
+
+/// How a value is sampled at coordinates between the cells of a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// The value of the nearest cell, without blending. Nothing is returned if it is nodata.
+    Nearest,
+    /// Bilinear interpolation between the four nearest cells, like `get_interpolate`.
+    #[default]
+    Bilinear,
+    /// Bicubic interpolation between the sixteen nearest cells using the Catmull-Rom spline, which is smoother than bilinear
+    /// and passes through the value of every cell.
+    ///
+    /// Cells past the edges of the grid repeat the value of the edge cell. With `NodataPolicy::Skip`,
+    /// bilinear interpolation is used instead wherever the sixteen cells include nodata.
+    ///
+    /// The spline can overshoot the values of the cells. Where it overshoots past the values that the type of the grid
+    /// can hold, such as past 255 in a grid of `u8`, the result is clamped to the values of the sixteen cells.
+    Bicubic,
+}
+
+/// Samples the grid at `x` and `y` using `method`, shared by every grid type.
+///
+/// `get_index` is called to fetch the value of each cell that contributes to the result.
+/// Cells equal to the header's nodata value are handled according to `policy`.
+///
+/// # Errors
+/// Returns an error if `get_index` fails for a contributing cell.
+pub(crate) fn sample<T, U, F>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+    method: Interpolation,
+    policy: NodataPolicy,
+    mut get_index: F,
+) -> Result<Option<U>, Error>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+    F: FnMut(usize, usize) -> Result<U, Error>,
+{
+    match method {
+        Interpolation::Nearest => nearest(header, registration, x, y, &mut get_index),
+        Interpolation::Bilinear => bilinear(header, registration, x, y, policy, &mut get_index),
+        Interpolation::Bicubic => bicubic(header, registration, x, y, policy, &mut get_index),
+    }
+}
+
+/// Returns the rows that sampling at `x` and `y` with `method` may read, or nothing if the coordinates cannot be sampled.
+pub(crate) fn rows_needed<T, U>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+    method: Interpolation,
+) -> Option<RangeInclusive<usize>>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    let (row, _) = lattice_position(header, registration, x, y)?;
+    let nrows = header.num_rows();
+    Some(match method {
+        Interpolation::Nearest => {
+            let (row, _) = header.index_of(x, y)?;
+            row..=row
+        }
+        Interpolation::Bilinear => {
+            let (top, bottom, _) = neighbours(row, nrows);
+            top..=bottom
+        }
+        // Also covers the rows read when falling back to bilinear
+        Interpolation::Bicubic => {
+            let top = row.floor();
+            clamp_index(top - 1.0, nrows)..=clamp_index(top + 2.0, nrows)
+        }
+    })
+}
+
+/// Returns the position of `x` and `y` in the grid as a fractional row and column, where the value of each cell lies on its whole row and column.
+///
+/// The value of a cell lies at its centre. Nothing is returned if the coordinates cannot be sampled under `registration`,
+/// and positions between the outermost centres and the edge of a `PixelIsArea` grid are moved onto the outermost centres.
+fn lattice_position<T, U>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+) -> Option<(f64, f64)>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    if x < header.min_x() || x > header.max_x() || y < header.min_y() || y > header.max_y() {
+        return None;
+    }
+    let to_f64 = |value: T| -> f64 { NumCast::from(value).unwrap() };
+    let col = to_f64(x - header.min_x()) / to_f64(header.cell_size_x()) - 0.5;
+    let row = to_f64(header.max_y() - y) / to_f64(header.cell_size_y()) - 0.5;
+    let last_row = (header.num_rows() - 1) as f64;
+    let last_col = (header.num_cols() - 1) as f64;
+    match registration {
+        CellRegistration::PixelIsArea => Some((row.clamp(0.0, last_row), col.clamp(0.0, last_col))),
+        CellRegistration::PixelIsPoint => {
+            let inside = (0.0..=last_row).contains(&row) && (0.0..=last_col).contains(&col);
+            inside.then_some((row, col))
+        }
+    }
+}
+
+/// Clamps a row or column on the lattice to the cells of the grid.
+fn clamp_index(index: f64, len: usize) -> usize {
+    index.clamp(0.0, (len - 1) as f64) as usize
+}
+
+/// Splits a position on the lattice into the two cells either side of it, and how far it is from the first towards the second.
+///
+/// If there is only one cell, both are that cell.
+fn neighbours(position: f64, len: usize) -> (usize, usize, f64) {
+    let first = clamp_index(position.floor(), len.saturating_sub(1).max(1));
+    let second = (first + 1).min(len - 1);
+    (first, second, position - first as f64)
+}
+
+/// The value of the cell nearest to `x` and `y`, or nothing if it is nodata or the coordinates cannot be sampled.
+///
+/// # Errors
+/// Returns an error if `get_index` fails for a contributing cell.
+fn nearest<T, U, F>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+    get_index: &mut F,
+) -> Result<Option<U>, Error>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+    F: FnMut(usize, usize) -> Result<U, Error>,
+{
+    if lattice_position(header, registration, x, y).is_none() {
+        return Ok(None);
+    }
+    // The nearest centre is that of the containing cell, and index_of settles points on the edge between two cells the same way as `get`
+    let Some((row, col)) = header.index_of(x, y) else {
+        return Ok(None);
+    };
+    Ok(header.mask(get_index(row, col)?))
+}
+
+/// The weights of the four lattice points around a fraction `t` of the way between the middle two, for the Catmull-Rom spline.
+fn catmull_rom(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Bicubic interpolation between the sixteen cells nearest to `x` and `y`, using the Catmull-Rom spline.
+///
+/// # Errors
+/// Returns an error if `get_index` fails for a contributing cell.
+fn bicubic<T, U, F>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+    policy: NodataPolicy,
+    get_index: &mut F,
+) -> Result<Option<U>, Error>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+    F: FnMut(usize, usize) -> Result<U, Error>,
+{
+    let Some((row, col)) = lattice_position(header, registration, x, y) else {
+        return Ok(None);
+    };
+    let (top, left) = (row.floor(), col.floor());
+    let row_weights = catmull_rom(row - top);
+    let col_weights = catmull_rom(col - left);
+
+    let mut value = 0.0;
+    let mut range = None;
+    for (i, row_weight) in row_weights.into_iter().enumerate() {
+        let row = clamp_index(top + i as f64 - 1.0, header.num_rows());
+        for (j, col_weight) in col_weights.into_iter().enumerate() {
+            let weight = row_weight * col_weight;
+            if weight == 0.0 {
+                continue;
+            }
+            let col = clamp_index(left + j as f64 - 1.0, header.num_cols());
+            let Some(cell) = header.mask(get_index(row, col)?) else {
+                // The weights of a spline can be negative, so they cannot be scaled up to make up for a missing cell
+                return match policy {
+                    NodataPolicy::Skip => bilinear(header, registration, x, y, policy, get_index),
+                    NodataPolicy::Refuse => Ok(None),
+                };
+            };
+            range = Some(widen(range, cell));
+            value += <f64 as NumCast>::from(cell).unwrap() * weight;
+        }
+    }
+    Ok(to_value(value, range))
+}
+
+/// Bilinear interpolation between the four cells nearest to `x` and `y`, shared by every grid type.
+///
+/// `get_index` is called to fetch the value of each cell that contributes to the result.
+/// Cells equal to the header's nodata value are handled according to `policy`.
+///
+/// # Errors
+/// Returns an error if `get_index` fails for a contributing cell.
+pub(crate) fn bilinear<T, U, F>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    registration: CellRegistration,
+    x: T,
+    y: T,
+    policy: NodataPolicy,
+    mut get_index: F,
+) -> Result<Option<U>, Error>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+    F: FnMut(usize, usize) -> Result<U, Error>,
+{
+    let Some((row, col)) = lattice_position(header, registration, x, y) else {
+        return Ok(None);
+    };
+    let (top, bottom, down) = neighbours(row, header.num_rows());
+    let (left, right, across) = neighbours(col, header.num_cols());
+
+    let cells = [
+        (top, left, (1.0 - across) * (1.0 - down)),
+        (top, right, across * (1.0 - down)),
+        (bottom, left, (1.0 - across) * down),
+        (bottom, right, across * down),
+    ];
+    blend(header, policy, cells, &mut get_index)
+}
+
+/// Takes the weighted sum of the given cells, leaving out or refusing nodata cells according to `policy`.
+///
+/// Cells with a weight of zero do not contribute, so they are never read.
+fn blend<T, U, F, const N: usize>(
+    header: &EsriASCIIRasterHeader<T, U>,
+    policy: NodataPolicy,
+    cells: [(usize, usize, f64); N],
+    get_index: &mut F,
+) -> Result<Option<U>, Error>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+    F: FnMut(usize, usize) -> Result<U, Error>,
+{
+    let mut value = 0.0;
+    let mut range = None;
+    let mut total_weight = 0.0;
+    let mut skipped = false;
+    for (row, col, weight) in cells {
+        if weight == 0.0 {
+            continue;
+        }
+        let Some(cell) = header.mask(get_index(row, col)?) else {
+            match policy {
+                NodataPolicy::Skip => {
+                    skipped = true;
+                    continue;
+                }
+                NodataPolicy::Refuse => return Ok(None),
+            }
+        };
+        range = Some(widen(range, cell));
+        value += <f64 as NumCast>::from(cell).unwrap() * weight;
+        total_weight += weight;
+    }
+    if total_weight == 0.0 {
+        return Ok(None);
+    }
+    // Only rescale when a cell was left out, so that complete neighbourhoods are unaffected by rounding
+    if skipped {
+        value /= total_weight;
+    }
+    Ok(to_value(value, range))
+}
+
+/// Widens the range of the cells that contribute to a result to include `cell`.
+fn widen<U: PartialOrd + Copy>(range: Option<(U, U)>, cell: U) -> (U, U) {
+    match range {
+        Some((lowest, highest)) => (
+            if cell < lowest { cell } else { lowest },
+            if cell > highest { cell } else { highest },
+        ),
+        None => (cell, cell),
+    }
+}
+
+/// Converts an interpolated value to type `U`.
+///
+/// A value that `U` cannot represent, such as the overshoot of the spline past the largest value of an integer type,
+/// is clamped to the range of the cells it was interpolated from.
+fn to_value<U: NumCast + Copy>(value: f64, range: Option<(U, U)>) -> Option<U> {
+    U::from(value).or_else(|| {
+        let (lowest, highest) = range?;
+        if value >= <f64 as NumCast>::from(highest)? {
+            Some(highest)
+        } else if value <= <f64 as NumCast>::from(lowest)? {
+            Some(lowest)
+        } else {
+            None
+        }
+    })
+}
@@ -76,8 +76,26 @@
     clippy::float_cmp
 )]
 pub mod ascii_file;
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+pub mod cache;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compression;
 pub mod error;
+#[cfg(feature = "fast-parse")]
+mod fast_parse;
 pub mod header;
+pub mod index;
+pub mod interpolate;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod raster;
+pub mod shared;
+pub mod stream;
+mod tokenizer;
+pub mod writer;
 
 pub use error::Error;
 
@@ -85,15 +103,41 @@ pub use error::Error;
 mod tests {
     use std::{
         fs::File,
-        io::{BufReader, Read, Seek},
+        io::{BufReader, Cursor, Read, Seek},
     };
 
     use crate::{
-        ascii_file::EsriASCIIReader,
+        ascii_file::{self, DataLayout, EsriASCIIReader},
+        cache::{CachePolicy, CacheStats},
         error,
         header::{EsriASCIIRasterHeader, Numerical},
+        index::RowIndex,
+        interpolate::{CellRegistration, Interpolation, NodataPolicy},
+        raster::EsriASCIIRaster,
+        shared::SharedEsriASCIIReader,
+        stream::EsriASCIIStreamReader,
+        writer::{EsriASCIIWriter, NumberFormat, WriteOptions},
     };
 
+    fn read_rows<R, T, U>(
+        grid: EsriASCIIReader<R, T, U>,
+    ) -> (EsriASCIIRasterHeader<T, U>, Vec<Vec<U>>)
+    where
+        R: Read + Seek,
+        T: Numerical,
+        error::Error: From<<T as Numerical>::Err>,
+        U: Numerical,
+        error::Error: From<<U as Numerical>::Err>,
+    {
+        let header = grid.header;
+        let mut rows = vec![Vec::with_capacity(header.ncols); header.nrows];
+        for cell in grid {
+            let (row, _, value) = cell.unwrap();
+            rows[row].push(value);
+        }
+        (header, rows)
+    }
+
     #[test]
     fn test_header() {
         let file = File::open("test_data/test.asc").unwrap();
@@ -106,7 +150,8 @@ mod tests {
         assert_eq!(header.xll as i32, 390_000);
         assert_eq!(header.yll as i32, 344_000);
         assert_eq!(header.cornertype, crate::header::CornerType::Corner);
-        assert_eq!(header.cellsize as i32, 1);
+        assert_eq!(header.dx as i32, 1);
+        assert_eq!(header.dy as i32, 1);
         assert_eq!(header.nodata_value, Some(-3.402_823_466_385_288_598_1e+38));
     }
 
@@ -137,6 +182,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_get() {
         let file = File::open("test_data/test.asc").unwrap();
         let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
@@ -194,6 +240,7 @@ mod tests {
         assert_eq!(grid_size, num_elements);
     }
     #[test]
+    #[allow(deprecated)]
     fn test_index_pos() {
         let file = File::open("test_data/test.asc").unwrap();
         let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
@@ -257,6 +304,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_get_interp() {
         let file = File::open("test_data/test.asc").unwrap();
         let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
@@ -264,13 +312,19 @@ mod tests {
         let lr = grid.get_index(999, 1).unwrap();
         let ul = grid.get_index(998, 0).unwrap();
         let ur = grid.get_index(998, 1).unwrap();
+        let half = grid.header.cell_size() / 2.;
 
-        // Spot check a few values
+        // Spot check a few values, where the value of each cell lies at its centre
+        assert_eq!(
+            grid.get_interpolate(grid.header.min_x() + half, grid.header.min_y() + half)
+                .unwrap(),
+            ll
+        );
         let expected1 = (ll + lr + ul + ur) / 4.;
         let val1 = grid
             .get_interpolate(
-                grid.header.min_x() + grid.header.cell_size() / 2.,
-                grid.header.min_y() + grid.header.cell_size() / 2.,
+                grid.header.min_x() + grid.header.cell_size(),
+                grid.header.min_y() + grid.header.cell_size(),
             )
             .unwrap();
         assert_eq!(val1, expected1);
@@ -278,21 +332,17 @@ mod tests {
         let expected2 = ll * 0.5625 + lr * 0.1875 + ul * 0.1875 + ur * 0.0625;
         let val2 = grid
             .get_interpolate(
-                grid.header.min_x() + grid.header.cell_size() / 4.,
-                grid.header.min_y() + grid.header.cell_size() / 4.,
+                grid.header.min_x() + half + grid.header.cell_size() / 4.,
+                grid.header.min_y() + half + grid.header.cell_size() / 4.,
             )
             .unwrap();
         assert_eq!(val2, expected2);
 
-        // At max_x and max_y there are fewer cells to interpolate with, so the interpolated value will be the same as the value as the lower left cell
+        // At the centre of the top right cell, only that cell contributes
         assert_eq!(
-            grid.get_interpolate(
-                grid.header.max_x() - grid.header.cell_size() / 2.,
-                grid.header.max_y() - grid.header.cell_size() / 2.
-            )
-            .unwrap(),
-            grid.get_index(grid.header.num_rows() - 1, grid.header.num_cols() - 1)
-                .unwrap()
+            grid.get_interpolate(grid.header.max_x() - half, grid.header.max_y() - half)
+                .unwrap(),
+            grid.get_index(0, grid.header.num_cols() - 1).unwrap()
         );
 
         // Bounds check
@@ -325,6 +375,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_many_gets() {
         let file = File::open("test_data/test.asc").unwrap();
         let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
@@ -356,6 +407,7 @@ mod tests {
         }
     }
     #[test]
+    #[allow(deprecated)]
     fn test_corner_types() {
         let xll = 0.; // From the test data files.
         let yll = 0.;
@@ -373,7 +425,8 @@ mod tests {
         // Assert that everything is the same except for the corner type
         assert_eq!(header_center.ncols, header_corner.ncols);
         assert_eq!(header_center.nrows, header_corner.nrows);
-        assert_eq!(header_center.cellsize, header_corner.cellsize);
+        assert_eq!(header_center.dx, header_corner.dx);
+        assert_eq!(header_center.dy, header_corner.dy);
         assert_eq!(header_center.nodata_value, header_corner.nodata_value);
         // Collect both iterators and confirm that they are the same
         let iter_center = grid_center.into_iter();
@@ -552,6 +605,1904 @@ mod tests {
         multiple_grids.compare_to(100., 150., 35.);
     }
 
+    #[test]
+    fn test_write_round_trip() {
+        for path in [
+            "test_data/test_llcorner.asc",
+            "test_data/test_llcenter.asc",
+            "test_data/test_ints.asc",
+        ] {
+            let file = File::open(path).unwrap();
+            let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+            let (header, rows) = read_rows(grid);
+
+            let bytes = EsriASCIIWriter::new(Vec::new(), header)
+                .write_grid(&rows)
+                .unwrap();
+            let written: EsriASCIIReader<_, f64, f64> =
+                EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+            let (written_header, written_rows) = read_rows(written);
+            assert_eq!(written_header, header);
+            assert_eq!(written_rows, rows);
+        }
+    }
+
+    #[test]
+    fn test_write_matches_source() {
+        // Integer grids have no formatting ambiguity, so the output should match the source byte for byte
+        let source = std::fs::read_to_string("test_data/test_ints.asc").unwrap();
+        let file = File::open("test_data/test_ints.asc").unwrap();
+        let grid: EsriASCIIReader<File, i32, i32> = EsriASCIIReader::from_file(file).unwrap();
+        let (header, rows) = read_rows(grid);
+        let bytes = EsriASCIIWriter::new(Vec::new(), header)
+            .write_grid(&rows)
+            .unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), source);
+    }
+
+    #[test]
+    fn test_write_rows() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let (header, rows) = read_rows(grid);
+
+        // Too few rows
+        let mut writer = EsriASCIIWriter::new(Vec::new(), header);
+        for row in &rows[..header.nrows - 1] {
+            writer.write_row(row).unwrap();
+        }
+        assert!(matches!(
+            writer.finish(),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+
+        // Too many rows
+        let mut writer = EsriASCIIWriter::new(Vec::new(), header);
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        assert_eq!(writer.rows_written(), header.nrows);
+        assert!(matches!(
+            writer.write_row(&rows[0]),
+            Err(error::Error::MismatchedRowCount(6, 7))
+        ));
+
+        // Wrong number of columns
+        let mut writer = EsriASCIIWriter::new(Vec::new(), header);
+        assert!(matches!(
+            writer.write_row(&rows[0][1..]),
+            Err(error::Error::MismatchColumnCount(4, 3))
+        ));
+        assert!(matches!(
+            writer.write_row(&[0.0; 5]),
+            Err(error::Error::MismatchColumnCount(4, 5))
+        ));
+        assert_eq!(writer.rows_written(), 0);
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let written: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+        assert_eq!(read_rows(written), (header, rows));
+    }
+
+    /// Values that are awkward to print, including subnormals, extremes and values with long expansions
+    const AWKWARD_VALUES: &str = "0.1 -0.2 0.30000000000000004 1e-45 1.17549435e-38 3.4028235e38\n\
+        -3.4028234663852886e38 2.2250738585072014e-308 5e-324 1.7976931348623157e308 123456789.123456789 -0\n";
+
+    #[test]
+    fn test_write_shortest_bit_exact() {
+        fn round_trip<U>(source: &str) -> (Vec<Vec<U>>, Vec<Vec<U>>)
+        where
+            U: Numerical + std::fmt::Display,
+            error::Error: From<<U as Numerical>::Err>,
+        {
+            let grid: EsriASCIIReader<_, U, U> =
+                EsriASCIIReader::from_file(Cursor::new(source.as_bytes().to_vec())).unwrap();
+            let (header, rows) = read_rows(grid);
+            let options = WriteOptions {
+                format: NumberFormat::Shortest,
+                nodata_token: None,
+            };
+            let bytes = EsriASCIIWriter::with_options(Vec::new(), header, options)
+                .write_grid(&rows)
+                .unwrap();
+            let written: EsriASCIIReader<_, U, U> =
+                EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+            let (written_header, written_rows) = read_rows(written);
+            assert_eq!(written_header, header);
+            (rows, written_rows)
+        }
+        let source = format!(
+            "ncols 6\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\nNODATA_value -9999\n{AWKWARD_VALUES}"
+        );
+
+        let (rows, written_rows) = round_trip::<f64>(&source);
+        let bits = |rows: &[Vec<f64>]| -> Vec<u64> {
+            rows.iter().flatten().map(|v| v.to_bits()).collect()
+        };
+        assert_eq!(bits(&rows), bits(&written_rows));
+
+        let (rows, written_rows) = round_trip::<f32>(&source);
+        let bits = |rows: &[Vec<f32>]| -> Vec<u32> {
+            rows.iter().flatten().map(|v| v.to_bits()).collect()
+        };
+        assert_eq!(bits(&rows), bits(&written_rows));
+    }
+
+    #[test]
+    fn test_write_number_formats() {
+        let header: EsriASCIIRasterHeader<f64, f64> = EsriASCIIRasterHeader::new(
+            5,
+            1,
+            0.,
+            0.,
+            crate::header::CornerType::Corner,
+            1.,
+            Some(-9999.),
+        );
+        let row = [1234.5678, 0.000_012_345, -2.5, 1e22, -9999.];
+        let write = |format| {
+            let options = WriteOptions {
+                format,
+                nodata_token: None,
+            };
+            let bytes = EsriASCIIWriter::with_options(Vec::new(), header, options)
+                .write_grid([row])
+                .unwrap();
+            let text = String::from_utf8(bytes).unwrap();
+            text.lines().last().unwrap().to_owned()
+        };
+        assert_eq!(
+            write(NumberFormat::Shortest),
+            "1234.5678 0.000012345 -2.5 10000000000000000000000 -9999"
+        );
+        assert_eq!(
+            write(NumberFormat::Fixed(2)),
+            "1234.57 0.00 -2.50 10000000000000000000000.00 -9999"
+        );
+        assert_eq!(
+            write(NumberFormat::Significant(3)),
+            "1.23e3 0.0000123 -2.50 1.00e22 -9999"
+        );
+        assert_eq!(
+            write(NumberFormat::Scientific(1)),
+            "1.2e3 1.2e-5 -2.5e0 1.0e22 -9999"
+        );
+
+        // A distinct nodata token replaces both the header value and the nodata cells
+        let options = WriteOptions {
+            format: NumberFormat::Fixed(1),
+            nodata_token: Some("-1".into()),
+        };
+        let bytes = EsriASCIIWriter::with_options(Vec::new(), header, options)
+            .write_grid([row])
+            .unwrap();
+        let mut grid: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+        assert_eq!(grid.header.no_data_value(), Some(-1.));
+        assert_eq!(grid.get_index(0, 4).unwrap(), -1.);
+        assert_eq!(grid.get_index(0, 0).unwrap(), 1234.6);
+    }
+
+    #[test]
+    fn test_raster() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        // Cache a row first, so that load_all has to mix cached and uncached rows
+        assert_eq!(grid.get_index(3, 1).unwrap(), 42.);
+        let mut raster = grid.load_all().unwrap();
+
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let (header, rows) = read_rows(EsriASCIIReader::<File, f64, f64>::from_file(file).unwrap());
+        assert_eq!(raster.header, header);
+        assert_eq!(raster.data(), rows.concat().as_slice());
+        for (row, values) in rows.iter().enumerate() {
+            assert_eq!(raster.row(row).unwrap(), values.as_slice());
+        }
+        assert!(raster.row(header.nrows).is_none());
+        assert_eq!(
+            raster.column(1).unwrap().copied().collect::<Vec<_>>(),
+            vec![-9999., 20., 8., 42., 75., 5.]
+        );
+        assert!(raster.column(header.ncols).is_none());
+        assert_eq!(raster.rows().count(), header.nrows);
+
+        // Coordinate lookups agree with the reader
+        for (x, y) in [
+            (0., 0.),
+            (25., 160.),
+            (199., 299.),
+            (200., 300.),
+            (120., 30.),
+        ] {
+            assert_eq!(raster.get(x, y), grid.get(x, y));
+            assert_eq!(raster.get_interpolate(x, y), grid.get_interpolate(x, y));
+        }
+        assert!(raster.get(-1., 0.).is_none());
+        assert!(raster.get_index(0, header.ncols).is_err());
+
+        raster.set_index(5, 3, 7.).unwrap();
+        assert_eq!(raster.get_index(5, 3).unwrap(), 7.);
+        assert!(raster.set_index(header.nrows, 0, 7.).is_err());
+        raster.row_mut(0).unwrap().fill(1.);
+        assert_eq!(raster.row(0).unwrap(), &[1.; 4]);
+
+        // The raster can be shared between threads
+        std::thread::scope(|scope| {
+            for row in 0..header.nrows {
+                let raster = &raster;
+                scope.spawn(move || raster.get_index(row, 0).unwrap());
+            }
+        });
+
+        let bytes = raster.write_to(Vec::new()).unwrap();
+        let mut written: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+        assert_eq!(written.load_all().unwrap(), raster);
+
+        assert!(matches!(
+            EsriASCIIRaster::new(header, vec![0.; 3]),
+            Err(error::Error::MismatchedCellCount(24, 3))
+        ));
+        let empty: EsriASCIIRasterHeader<f64, f64> =
+            EsriASCIIRasterHeader::new(0, 3, 0., 0., crate::header::CornerType::Corner, 1., None);
+        assert!(matches!(
+            EsriASCIIRaster::new(empty, vec![]),
+            Err(error::Error::BrokenInvariant(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_any_order() {
+        let source = "NODATA_VALUE -1\nCellSize 2\n\n  yllcenter 10\nNROWS 2\nxllcenter 20\nncols 3\n1 2 3\n4 5 -1\n";
+        let mut grid: EsriASCIIReader<_, f64, i32> =
+            EsriASCIIReader::from_file(Cursor::new(source)).unwrap();
+        let expected = EsriASCIIRasterHeader::new(
+            3,
+            2,
+            20.,
+            10.,
+            crate::header::CornerType::Center,
+            2.,
+            Some(-1),
+        );
+        assert_eq!(grid.header, expected);
+        assert_eq!(grid.get_index(0, 0).unwrap(), 1);
+        assert_eq!(grid.get_index(1, 2).unwrap(), -1);
+
+        // Special float values at the start of the grid are not mistaken for keys
+        let source = "ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\nnan inf\n";
+        let mut grid: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(source)).unwrap();
+        assert!(grid.get_index(0, 0).unwrap().is_nan());
+        assert!(grid.get_index(0, 1).unwrap().is_infinite());
+    }
+
+    #[test]
+    fn test_header_errors() {
+        fn header_err(source: &str) -> error::Error {
+            EsriASCIIReader::<_, f64, f64>::from_file(Cursor::new(source.to_owned())).unwrap_err()
+        }
+        let full = "ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n";
+        assert!(matches!(
+            header_err(&format!("{full}NCOLS 2\n1\n")),
+            error::Error::DuplicateField(field) if field == "NCOLS"
+        ));
+        assert!(matches!(
+            header_err(&format!("{full}xllcenter 0\n1\n")),
+            error::Error::DuplicateField(field) if field == "xllcenter"
+        ));
+        assert!(matches!(
+            header_err(&format!("{full}projection utm\n1\n")),
+            error::Error::UnknownField(field) if field == "projection"
+        ));
+        assert!(matches!(
+            header_err("nrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1\n"),
+            error::Error::MissingField(field) if field == "ncols"
+        ));
+        assert!(matches!(
+            header_err("ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\n1\n"),
+            error::Error::MissingField(field) if field == "cellsize or dx and dy"
+        ));
+        assert!(matches!(
+            header_err("ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ndx 1\n1\n"),
+            error::Error::MissingField(field) if field == "dy"
+        ));
+        assert!(matches!(
+            header_err("ncols 1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\ndy 1\n1\n"),
+            error::Error::BrokenInvariant(_)
+        ));
+        assert!(matches!(
+            header_err("ncols\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1\n"),
+            error::Error::MissingValue(field) if field == "ncols"
+        ));
+        assert!(matches!(
+            header_err("ncols 1\nnrows 1\nxllcorner 0\nyllcenter 0\ncellsize 1\n1\n"),
+            error::Error::BrokenInvariant(_)
+        ));
+        assert!(matches!(
+            header_err("ncols -1\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n1\n"),
+            error::Error::TypeCast(..)
+        ));
+    }
+
+    #[test]
+    fn test_no_nodata() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let (_, expected_rows) =
+            read_rows(EsriASCIIReader::<File, f64, f64>::from_file(file).unwrap());
+
+        let file = File::open("test_data/test_no_nodata.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.header.no_data_value(), None);
+        assert_eq!(grid.header.num_rows(), 6);
+        // The first data row must not be swallowed by the header
+        assert_eq!(grid.get_index(0, 0).unwrap(), -9999.);
+        assert_eq!(grid.get_index(0, 2).unwrap(), 5.);
+        assert_eq!(grid.get_index(5, 2).unwrap(), 1.);
+        assert_eq!(grid.get(0., 0.).unwrap(), 13.);
+        assert!(grid.get_index(6, 0).is_err());
+
+        let file = File::open("test_data/test_no_nodata.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let (header, rows) = read_rows(grid);
+        assert_eq!(rows, expected_rows);
+
+        let bytes = EsriASCIIWriter::new(Vec::new(), header)
+            .write_grid(&rows)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            std::fs::read_to_string("test_data/test_no_nodata.asc")
+                .unwrap()
+                .replace(".0\n", "\n")
+        );
+    }
+
+    #[test]
+    fn test_missing_rows() {
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.get_index(4, 0).unwrap(), 88.);
+        assert!(matches!(
+            grid.get_index(5, 0),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let cells = grid.into_iter().collect::<Vec<_>>();
+        assert_eq!(cells.len(), 5 * 4 + 1);
+        assert!(cells[..20].iter().all(Result::is_ok));
+        assert!(matches!(
+            cells[20],
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+    }
+
+    #[test]
+    fn test_non_square_cells() {
+        let file = File::open("test_data/test_dxdy.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let header = grid.header;
+        assert_eq!(header.cell_size_x(), 50.);
+        assert_eq!(header.cell_size_y(), 25.);
+        assert_eq!(header.max_x(), 200.);
+        assert_eq!(header.max_y(), 150.);
+
+        assert_eq!(header.index_pos(0, 0).unwrap(), (0., 125.));
+        assert_eq!(header.index_pos(5, 3).unwrap(), (150., 0.));
+        assert_eq!(header.index_of(0., 0.).unwrap(), (5, 0));
+        assert_eq!(header.index_of(60., 30.).unwrap(), (4, 1));
+        assert_eq!(header.index_of(200., 150.).unwrap(), (0, 3));
+        assert!(header.index_of(0., 151.).is_none());
+        for row in 0..header.nrows {
+            for col in 0..header.ncols {
+                let (x, y) = header.index_pos(row, col).unwrap();
+                assert_eq!(header.index_of(x, y).unwrap(), (row, col));
+            }
+        }
+        assert_eq!(grid.get(60., 30.).unwrap(), 75.);
+
+        // Interpolating a quarter of the way between cell centres weights each axis by its own cell size
+        let ll = grid.get_index(5, 0).unwrap();
+        let lr = grid.get_index(5, 1).unwrap();
+        let ul = grid.get_index(4, 0).unwrap();
+        let ur = grid.get_index(4, 1).unwrap();
+        let expected = ll * 0.5625 + lr * 0.1875 + ul * 0.1875 + ur * 0.0625;
+        assert_eq!(grid.get_interpolate(37.5, 18.75).unwrap(), expected);
+
+        let center: EsriASCIIRasterHeader<f64, f64> = EsriASCIIRasterHeader::new_with_cell_sizes(
+            4,
+            6,
+            0.,
+            0.,
+            crate::header::CornerType::Center,
+            50.,
+            25.,
+            None,
+        );
+        assert_eq!(center.min_x(), -25.);
+        assert_eq!(center.min_y(), -12.5);
+
+        // dx and dy are written back when the cells are not square
+        let (header, rows) = read_rows(grid);
+        let bytes = EsriASCIIWriter::new(Vec::new(), header)
+            .write_grid(&rows)
+            .unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.contains("dx            50\ndy            25\n"));
+        let written: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(text)).unwrap();
+        assert_eq!(read_rows(written), (header, rows));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_cell_size() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.header.cell_size(), 50.);
+        assert_eq!(grid.header.cellsize, 50.);
+
+        // For a grid with non-square cells, the width of a cell is given
+        let file = File::open("test_data/test_dxdy.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.header.cell_size(), grid.header.cell_size_x());
+        assert_eq!(grid.header.cellsize, grid.header.cell_size_x());
+    }
+
+    #[test]
+    fn test_row_tokenizer() {
+        // Rows are found the same way however the data is split between buffers
+        let cases = [
+            (DataLayout::Lines, "1 2\n3.5 -4\r\n5 6"),
+            (DataLayout::Stream, "  1\n2 3.5\r\n -4 5\n\n6 \n"),
+        ];
+        for (layout, data) in cases {
+            for capacity in 1..=data.len() {
+                let mut reader = BufReader::with_capacity(capacity, data.as_bytes());
+                let mut line = String::new();
+                let mut rows = Vec::new();
+                while let Some(values) = ascii_file::read_next_row::<_, f64>(
+                    &mut reader,
+                    layout,
+                    rows.len(),
+                    2,
+                    &mut line,
+                )
+                .unwrap()
+                {
+                    rows.push(values);
+                }
+                assert_eq!(
+                    rows,
+                    [vec![1., 2.], vec![3.5, -4.], vec![5., 6.]],
+                    "{layout:?}, {capacity}"
+                );
+            }
+        }
+
+        // A stream that ends partway through a row
+        let mut reader = BufReader::with_capacity(2, "1 2 3".as_bytes());
+        let mut line = String::new();
+        let read = |reader: &mut BufReader<_>, line: &mut String| {
+            ascii_file::read_next_row::<_, f64>(reader, DataLayout::Stream, 0, 2, line)
+        };
+        assert_eq!(read(&mut reader, &mut line).unwrap(), Some(vec![1., 2.]));
+        assert!(matches!(
+            read(&mut reader, &mut line),
+            Err(error::Error::MismatchColumnCount(2, 1))
+        ));
+    }
+
+    #[test]
+    fn test_stream_layout() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let (expected_header, expected_rows) =
+            read_rows(EsriASCIIReader::<File, f64, f64>::from_file(file).unwrap());
+
+        for path in [
+            "test_data/test_wrapped.asc",
+            "test_data/test_single_line.asc",
+            "test_data/test_llcorner.asc",
+        ] {
+            let open = || -> EsriASCIIReader<File, f64, f64> {
+                let file = File::open(path).unwrap();
+                EsriASCIIReader::from_file(file)
+                    .unwrap()
+                    .with_layout(DataLayout::Stream)
+            };
+            let mut grid = open();
+            assert_eq!(grid.layout(), DataLayout::Stream);
+            // Random access, starting from the bottom so that the row positions have to be discovered
+            for row in (0..expected_header.nrows).rev() {
+                for col in (0..expected_header.ncols).rev() {
+                    assert_eq!(grid.get_index(row, col).unwrap(), expected_rows[row][col]);
+                }
+            }
+            assert_eq!(grid.load_all().unwrap().data(), expected_rows.concat());
+            assert_eq!(read_rows(open()), (expected_header, expected_rows.clone()));
+        }
+
+        // The line layout rejects wrapped rows rather than misplacing values
+        let file = File::open("test_data/test_wrapped.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert!(matches!(
+            grid.get_index(0, 0),
+            Err(error::Error::MismatchColumnCount(4, 3))
+        ));
+        let file = File::open("test_data/test_wrapped.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let mut iter = grid.into_iter();
+        assert!(matches!(
+            iter.next(),
+            Some(Err(error::Error::MismatchColumnCount(4, 3)))
+        ));
+        assert!(iter.next().is_none());
+
+        // A stream that ends part way through a row is reported
+        let source = "ncols 4\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2 3\n4 5\n";
+        let mut grid: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(source))
+                .unwrap()
+                .with_layout(DataLayout::Stream);
+        assert_eq!(grid.get_index(0, 3).unwrap(), 4.);
+        assert!(matches!(
+            grid.get_index(1, 0),
+            Err(error::Error::MismatchColumnCount(4, 1))
+        ));
+    }
+
+    #[test]
+    fn test_masked() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.get_index_masked(0, 0).unwrap(), None);
+        assert_eq!(grid.get_index_masked(5, 3).unwrap(), None);
+        assert_eq!(grid.get_index_masked(5, 2).unwrap(), Some(1.));
+        assert!(grid.get_index_masked(6, 0).is_err());
+        assert_eq!(grid.get_masked(10., 290.), None);
+        assert_eq!(grid.get_masked(110., 10.), Some(1.));
+        assert_eq!(grid.get_masked(-10., 10.), None);
+
+        let raster = grid.load_all().unwrap();
+        assert_eq!(raster.get_index_masked(0, 0).unwrap(), None);
+        assert_eq!(raster.get_index_masked(5, 2).unwrap(), Some(1.));
+        assert_eq!(raster.get_masked(10., 290.), None);
+        assert_eq!(raster.get_masked(110., 10.), Some(1.));
+
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let masked = grid
+            .into_iter()
+            .masked()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(masked.len(), 24);
+        let nodata = masked
+            .iter()
+            .filter(|(_, _, value)| value.is_none())
+            .map(|&(row, col, _)| (row, col))
+            .collect::<Vec<_>>();
+        assert_eq!(nodata, vec![(0, 0), (0, 1), (1, 0), (5, 3)]);
+
+        // A grid without a nodata value never masks anything
+        let file = File::open("test_data/test_no_nodata.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert_eq!(grid.get_index_masked(0, 0).unwrap(), Some(-9999.));
+
+        // A NaN nodata value masks NaN cells, even though NaN never equals itself
+        let source =
+            "ncols 3\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\nNODATA_value nan\n1 NaN 3\n";
+        let mut grid: EsriASCIIReader<_, f64, f64> =
+            EsriASCIIReader::from_file(Cursor::new(source)).unwrap();
+        assert!(grid.header.is_nodata(f64::NAN));
+        assert!(!grid.header.is_nodata(1.));
+        assert_eq!(grid.get_index_masked(0, 1).unwrap(), None);
+        assert_eq!(grid.get_index_masked(0, 2).unwrap(), Some(3.));
+        let raster = grid.load_all().unwrap();
+        assert_eq!(raster.get_masked(1.5, 0.5), None);
+        let written = String::from_utf8(raster.write_to(Vec::new()).unwrap()).unwrap();
+        assert!(written.ends_with("1 NaN 3\n"));
+        let masked = grid
+            .into_iter()
+            .masked()
+            .map(|cell| cell.unwrap().2)
+            .collect::<Vec<_>>();
+        assert_eq!(masked, vec![Some(1.), None, Some(3.)]);
+    }
+
+    #[test]
+    fn test_interpolate_nodata() {
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let raster = grid.load_all().unwrap();
+
+        // No nodata neighbours, so both policies agree
+        let expected = (8. + 35. + 20. + 100.) / 4.;
+        for policy in [NodataPolicy::Skip, NodataPolicy::Refuse] {
+            assert_eq!(
+                grid.get_interpolate_with(100., 200., policy).unwrap(),
+                Some(expected)
+            );
+            assert_eq!(
+                raster.get_interpolate_with(100., 200., policy),
+                Some(expected)
+            );
+        }
+
+        // One of the four neighbours is nodata
+        assert_eq!(
+            grid.get_interpolate_with(100., 250., NodataPolicy::Refuse)
+                .unwrap(),
+            None
+        );
+        let skipped = grid
+            .get_interpolate_with(100., 250., NodataPolicy::Skip)
+            .unwrap()
+            .unwrap();
+        assert!((skipped - (20. + 100. + 5.) / 3.).abs() < 1e-9);
+        assert_eq!(grid.get_interpolate(100., 250.), Some(skipped));
+        assert_eq!(raster.get_interpolate(100., 250.), Some(skipped));
+
+        // Exactly on the centre of a nodata cell, no other cell contributes
+        for policy in [NodataPolicy::Skip, NodataPolicy::Refuse] {
+            assert_eq!(grid.get_interpolate_with(25., 225., policy).unwrap(), None);
+        }
+        // Exactly on the centre of a valid cell next to nodata, the nodata cells have no weight
+        assert_eq!(
+            grid.get_interpolate_with(75., 225., NodataPolicy::Refuse)
+                .unwrap(),
+            Some(20.)
+        );
+
+        // A missing row is returned as an error rather than panicking
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert!(matches!(
+            grid.get_interpolate_with(25., 25., NodataPolicy::Skip),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+    }
+
+    #[test]
+    fn test_interpolate_planes() {
+        let plane = |x: f64, y: f64| 3. * x - 2. * y + 7.;
+        // The registration is taken from the header, and can be overridden whichever way the header positions the grid
+        let files = ["test_data/test_llcorner.asc", "test_data/test_llcenter.asc"];
+        for (path, registration) in files.into_iter().zip([
+            CellRegistration::PixelIsArea,
+            CellRegistration::PixelIsPoint,
+        ]) {
+            let file = File::open(path).unwrap();
+            let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+            assert_eq!(grid.header.cell_registration(), registration);
+            assert_eq!(grid.cell_registration(), registration);
+        }
+        let registrations = [
+            CellRegistration::PixelIsArea,
+            CellRegistration::PixelIsPoint,
+        ];
+        for (path, registration) in files
+            .into_iter()
+            .flat_map(|path| registrations.map(|registration| (path, registration)))
+        {
+            let file = File::open(path).unwrap();
+            let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+            let header = grid.header;
+            // Replace the values with a plane sampled at the centre of each cell
+            let centre = |row, col| {
+                let (x, y) = header.index_pos(row, col).unwrap();
+                (x + header.cell_size_x() / 2., y + header.cell_size_y() / 2.)
+            };
+            let mut data = Vec::new();
+            for row in 0..header.nrows {
+                for col in 0..header.ncols {
+                    let (x, y) = centre(row, col);
+                    data.push(plane(x, y));
+                }
+            }
+            let raster = EsriASCIIRaster::new(header, data)
+                .unwrap()
+                .with_cell_registration(registration);
+            let bytes = raster.write_to(Vec::new()).unwrap();
+            let mut grid: EsriASCIIReader<_, f64, f64> =
+                EsriASCIIReader::from_file(Cursor::new(bytes))
+                    .unwrap()
+                    .with_cell_registration(registration);
+            assert_eq!(grid.header, header);
+            assert_eq!(grid.load_all().unwrap(), raster);
+
+            // Between the outermost centres, bilinear interpolation recovers the plane
+            let (min_x, max_y) = centre(0, 0);
+            let (max_x, min_y) = centre(header.nrows - 1, header.ncols - 1);
+            for i in 0..=8 {
+                for j in 0..=8 {
+                    let x = min_x + (max_x - min_x) * f64::from(i) / 8.;
+                    let y = min_y + (max_y - min_y) * f64::from(j) / 8.;
+                    let value = grid.get_interpolate(x, y).unwrap();
+                    assert!((value - plane(x, y)).abs() < 1e-9, "{path}: {x}, {y}");
+                    assert_eq!(raster.get_interpolate(x, y), Some(value));
+                }
+            }
+            // Bicubic interpolation recovers it wherever the sixteen nearest cells are in the grid,
+            // given here relative to the lower left corner of the grid
+            for (x, y) in [(75., 175.), (90., 140.), (110., 190.), (125., 80.)] {
+                let (x, y) = (x + header.min_x(), y + header.min_y());
+                let value = grid.sample(x, y, Interpolation::Bicubic).unwrap().unwrap();
+                assert!((value - plane(x, y)).abs() < 1e-9, "{path}: {x}, {y}");
+            }
+            for row in 0..header.nrows {
+                for col in 0..header.ncols {
+                    let (x, y) = centre(row, col);
+                    let expected = grid.get_index(row, col).ok();
+                    assert_eq!(grid.sample(x, y, Interpolation::Nearest).unwrap(), expected);
+                    assert_eq!(grid.get_interpolate(x, y), expected);
+                }
+            }
+
+            // Outside the outermost centres, the edges depend on the registration
+            let edges = [
+                (header.min_x(), 150., (min_x, 150.)),
+                (header.max_x(), 150., (max_x, 150.)),
+                (100., header.min_y(), (100., min_y)),
+                (100., header.max_y(), (100., max_y)),
+                (header.min_x(), header.max_y(), (min_x, max_y)),
+            ];
+            for (x, y, (clamped_x, clamped_y)) in edges {
+                let value = grid.get_interpolate(x, y);
+                match registration {
+                    CellRegistration::PixelIsArea => {
+                        let value = value.unwrap();
+                        assert!((value - plane(clamped_x, clamped_y)).abs() < 1e-9);
+                    }
+                    CellRegistration::PixelIsPoint => {
+                        assert!(value.is_none());
+                        assert!(grid.sample(x, y, Interpolation::Nearest).unwrap().is_none());
+                        assert!(grid.sample(x, y, Interpolation::Bicubic).unwrap().is_none());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        // A plane, sampled at the centre of each cell
+        let plane = |x: f64, y: f64| 2. * x + 3. * y;
+        let header: EsriASCIIRasterHeader<f64, f64> = EsriASCIIRasterHeader::new(
+            6,
+            6,
+            0.,
+            0.,
+            crate::header::CornerType::Corner,
+            10.,
+            Some(-9999.),
+        );
+        let mut data = Vec::new();
+        for row in 0..6 {
+            for col in 0..6 {
+                let (x, y) = header.index_pos(row, col).unwrap();
+                data.push(plane(x + 5., y + 5.));
+            }
+        }
+        let raster = EsriASCIIRaster::new(header, data).unwrap();
+        for (x, y) in [(25., 25.), (22.5, 31.), (20., 20.), (29.9, 20.1)] {
+            let value = raster.sample(x, y, Interpolation::Bicubic).unwrap();
+            assert!((value - plane(x, y)).abs() < 1e-9, "{x}, {y}");
+            assert_eq!(
+                raster.sample(x, y, Interpolation::Bilinear),
+                raster.get_interpolate(x, y)
+            );
+        }
+        // The nearest centre is that of the cell containing the coordinates,
+        // and points on the edge between two cells go to the same cell as with get
+        for (x, y) in [
+            (14., 36.),
+            (16., 34.),
+            (0., 0.),
+            (59., 1.),
+            (15., 30.),
+            (20., 35.),
+            (10., 40.),
+            (60., 60.),
+        ] {
+            assert_eq!(
+                raster.sample(x, y, Interpolation::Nearest),
+                raster.get(x, y)
+            );
+        }
+        for method in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::Bicubic,
+        ] {
+            assert!(raster.sample(-1., 10., method).is_none());
+            assert!(raster.sample(10., 61., method).is_none());
+            // The edges clamp to the outermost cells
+            assert!(raster.sample(0., 0., method).is_some());
+            assert!(raster.sample(60., 60., method).is_some());
+        }
+
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let raster = grid.load_all().unwrap();
+        // Every cell passes through its own value
+        for row in 0..6 {
+            for col in 0..4 {
+                let (x, y) = grid.header.index_pos(row, col).unwrap();
+                let (x, y) = (x + 25., y + 25.);
+                let expected = grid.get_index_masked(row, col).unwrap();
+                for method in [Interpolation::Nearest, Interpolation::Bicubic] {
+                    assert_eq!(grid.sample(x, y, method).unwrap(), expected);
+                    assert_eq!(raster.sample(x, y, method), expected);
+                }
+            }
+        }
+        // The sixteen cells around this point include nodata, so skipping falls back to bilinear
+        assert_eq!(
+            grid.sample(100., 200., Interpolation::Bicubic).unwrap(),
+            grid.get_interpolate(100., 200.)
+        );
+        assert_eq!(grid.sample(0., 300., Interpolation::Nearest).unwrap(), None);
+
+        // A missing row is returned as an error rather than panicking
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        for method in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::Bicubic,
+        ] {
+            assert!(grid.sample(25., 275., method).is_ok());
+            assert!(matches!(
+                grid.sample(25., 25., method),
+                Err(error::Error::MismatchedRowCount(6, 5))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sample_overshoot() {
+        // Between the two high columns, the spline overshoots them by an eighth
+        fn overshoot<U>(high: U) -> Option<U>
+        where
+            U: Numerical,
+            error::Error: From<<U as Numerical>::Err> + From<<f64 as Numerical>::Err>,
+        {
+            let header: EsriASCIIRasterHeader<f64, U> = EsriASCIIRasterHeader::new(
+                4,
+                4,
+                0.,
+                0.,
+                crate::header::CornerType::Corner,
+                1.,
+                None,
+            );
+            let row = [U::zero(), high, high, U::zero()];
+            let raster = EsriASCIIRaster::new(header, row.repeat(4)).unwrap();
+            raster.sample(2., 1.5, Interpolation::Bicubic)
+        }
+        assert_eq!(overshoot(255_f64), Some(286.875));
+        assert_eq!(overshoot(255_i16), Some(286));
+        // Past the largest value of the type, the value is clamped to the cells
+        assert_eq!(overshoot(255_u8), Some(255));
+        assert_eq!(overshoot(i64::MAX), Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_sample_many() {
+        let open = |path| -> EsriASCIIReader<File, f64, f64> {
+            EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap()
+        };
+        // Scattered points in no particular order, some outside the grid
+        let mut seed = 12345_u64;
+        let mut next = || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as f64 / f64::from(1_u32 << 31)
+        };
+        let points = (0..500)
+            .map(|_| (next() * 240. - 30., next() * 340. - 30.))
+            .collect::<Vec<_>>();
+        for path in ["test_data/test_llcorner.asc", "test_data/test_llcenter.asc"] {
+            let mut grid = open(path);
+            for method in [
+                Interpolation::Nearest,
+                Interpolation::Bilinear,
+                Interpolation::Bicubic,
+            ] {
+                let values = grid.sample_many(&points, method).unwrap();
+                assert_eq!(values.len(), points.len());
+                assert!(values.iter().any(Option::is_some));
+                assert!(values.iter().any(Option::is_none));
+                let mut single = open(path);
+                for (&(x, y), value) in points.iter().zip(values) {
+                    assert_eq!(
+                        value,
+                        single.sample(x, y, method).unwrap(),
+                        "{path}: {x}, {y}"
+                    );
+                }
+            }
+            // The rows were read without going through the cache
+            assert_eq!(grid.cache_stats(), CacheStats::default());
+            assert!(
+                grid.sample_many(&[], Interpolation::Bilinear)
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+        // A missing row is an error rather than a panic
+        let mut grid = open("test_data/test_no_nodata_short.asc");
+        let points = [(25., 275.), (25., 25.)];
+        assert!(
+            grid.sample_many(&points[..1], Interpolation::Nearest)
+                .is_ok()
+        );
+        assert!(matches!(
+            grid.sample_many(&points, Interpolation::Nearest),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+    }
+
+    #[test]
+    fn test_row_index() {
+        let dir =
+            std::env::temp_dir().join(format!("esri_ascii_grid_index_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("grid.asc");
+        let sidecar = RowIndex::sidecar_path(&path);
+        assert_eq!(sidecar, dir.join("grid.asc.idx"));
+        std::fs::copy("test_data/test_llcorner.asc", &path).unwrap();
+        let _ = std::fs::remove_file(&sidecar);
+
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        let offsets = grid.row_offsets().unwrap();
+        assert_eq!(offsets.len(), 6);
+        let expected = grid.load_all().unwrap();
+
+        // Without a sidecar, the grid opens as usual and nothing is written
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert!(!sidecar.exists());
+        assert_eq!(grid.load_all().unwrap(), expected);
+
+        let built = RowIndex::build_sidecar::<f64, f64>(&path, DataLayout::Lines).unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        let index = RowIndex::read(File::open(&sidecar).unwrap()).unwrap();
+        assert_eq!(index, built);
+        assert_eq!(index.offsets, offsets);
+        assert_eq!(index.layout, DataLayout::Lines);
+        assert!(index.matches(&std::fs::metadata(&path).unwrap()));
+        assert_eq!(grid.get_index(5, 2).unwrap(), 1.);
+        assert_eq!(grid.load_all().unwrap(), expected);
+
+        // Later opens use the offsets from the sidecar, unless they are inconsistent with the grid, in which case the
+        // sidecar is rejected and the rows are found by scanning
+        let mut repeated = index.clone();
+        repeated.offsets[5] = offsets[4];
+        repeated.write(File::create(&sidecar).unwrap()).unwrap();
+        assert!(matches!(
+            RowIndex::read(File::open(&sidecar).unwrap()),
+            Err(error::Error::InvalidIndex(_))
+        ));
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.get_index(5, 0).unwrap(), 13.);
+
+        let mut past_end = index.clone();
+        past_end.offsets[5] = index.source_len;
+        past_end.write(File::create(&sidecar).unwrap()).unwrap();
+        assert!(matches!(
+            RowIndex::read(File::open(&sidecar).unwrap()),
+            Err(error::Error::InvalidIndex(_))
+        ));
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.get_index(5, 0).unwrap(), 13.);
+
+        let mut late = index.clone();
+        late.offsets[0] += 1;
+        late.write(File::create(&sidecar).unwrap()).unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.get_index(0, 0).unwrap(), -9999.);
+
+        // A sidecar for a different version of the file is ignored, and left as it is
+        let stale = RowIndex {
+            source_len: index.source_len + 1,
+            ..index.clone()
+        };
+        stale.write(File::create(&sidecar).unwrap()).unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.get_index(5, 0).unwrap(), 13.);
+        assert_eq!(
+            RowIndex::read(File::open(&sidecar).unwrap()).unwrap(),
+            stale
+        );
+
+        // As is a sidecar that is not an index at all
+        std::fs::write(&sidecar, b"not an index").unwrap();
+        assert!(RowIndex::read(File::open(&sidecar).unwrap()).is_err());
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.get_index(5, 0).unwrap(), 13.);
+
+        // The layout is recorded in the sidecar
+        std::fs::copy("test_data/test_wrapped.asc", &path).unwrap();
+        let index = RowIndex::build_sidecar::<f64, f64>(&path, DataLayout::Stream).unwrap();
+        assert_eq!(index.layout, DataLayout::Stream);
+        let mut grid: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file_with_index(&path).unwrap();
+        assert_eq!(grid.layout(), DataLayout::Stream);
+        assert_eq!(grid.load_all().unwrap(), expected);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_row_cache() {
+        let open = |policy| -> EsriASCIIReader<File, f64, f64> {
+            let file = File::open("test_data/test_llcorner.asc").unwrap();
+            EsriASCIIReader::from_file(file)
+                .unwrap()
+                .with_cache_policy(policy)
+        };
+        let rows = [0, 1, 0, 2, 0, 1, 5, 5];
+        let mut expected = vec![];
+        let mut grid = open(CachePolicy::Unbounded);
+        for row in rows {
+            expected.push(grid.get_index(row, 2).unwrap());
+        }
+        assert_eq!(expected, [5., 100., 5., 35., 5., 100., 1., 1.]);
+        assert_eq!(
+            grid.cache_stats(),
+            CacheStats {
+                hits: 4,
+                misses: 4,
+                evictions: 0,
+                rows: 4,
+                bytes: 4 * 4 * 8,
+            }
+        );
+
+        let stats = |policy| {
+            let mut grid = open(policy);
+            assert_eq!(grid.cache_policy(), policy);
+            for (&row, &value) in rows.iter().zip(&expected) {
+                assert_eq!(grid.get_index(row, 2).unwrap(), value);
+            }
+            let stats = grid.cache_stats();
+            (stats.hits, stats.misses, stats.evictions, stats.rows)
+        };
+        // Row 0 is used often enough to stay cached, so only row 1 is read twice
+        assert_eq!(stats(CachePolicy::MaxRows(2)), (3, 5, 3, 2));
+        // Two rows of four f64 values fit, a third does not
+        assert_eq!(stats(CachePolicy::MaxBytes(80)), (3, 5, 3, 2));
+        assert_eq!(stats(CachePolicy::MaxRows(1)), (1, 7, 6, 1));
+        // A limit too small for a single row caches nothing
+        assert_eq!(stats(CachePolicy::MaxBytes(16)), (0, 8, 0, 0));
+        assert_eq!(stats(CachePolicy::MaxRows(0)), (0, 8, 0, 0));
+        assert_eq!(stats(CachePolicy::Disabled), (0, 8, 0, 0));
+
+        // Loading everything neither counts towards nor fills a bounded cache
+        let mut grid = open(CachePolicy::MaxRows(2));
+        let raster = grid.load_all().unwrap();
+        assert_eq!(raster.get_index(5, 2).unwrap(), 1.);
+        assert_eq!(grid.cache_stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_shared_reader() {
+        fn assert_send_sync<V: Send + Sync>() {}
+        assert_send_sync::<SharedEsriASCIIReader<f64, f64>>();
+
+        for (path, layout) in [
+            ("test_data/test_llcorner.asc", DataLayout::Lines),
+            ("test_data/test_wrapped.asc", DataLayout::Stream),
+        ] {
+            let expected = EsriASCIIReader::<File, f64, f64>::from_file(File::open(path).unwrap())
+                .unwrap()
+                .with_layout(layout)
+                .load_all()
+                .unwrap();
+            let grid = EsriASCIIReader::<File, f64, f64>::from_file(File::open(path).unwrap())
+                .unwrap()
+                .with_layout(layout)
+                .with_cache_policy(CachePolicy::MaxRows(2))
+                .into_shared();
+            assert_eq!(grid.layout(), layout);
+            let header = grid.header;
+            // Each thread starts from a different row, so rows are found out of order
+            std::thread::scope(|scope| {
+                for start in 0..header.nrows {
+                    let grid = &grid;
+                    let expected = &expected;
+                    scope.spawn(move || {
+                        for row in (start..header.nrows).chain(0..start).rev() {
+                            for col in 0..header.ncols {
+                                assert_eq!(
+                                    grid.get_index(row, col).unwrap(),
+                                    expected.get_index(row, col).unwrap()
+                                );
+                            }
+                        }
+                    });
+                }
+            });
+            let stats = grid.cache_stats();
+            assert_eq!(
+                stats.hits + stats.misses,
+                (header.nrows * header.ncols * header.nrows) as u64
+            );
+            assert!(stats.rows <= 2);
+
+            for (x, y) in [(10., 10.), (75., 120.), (199., 299.), (-1., 0.)] {
+                assert_eq!(grid.get(x, y), expected.get(x, y));
+                assert_eq!(grid.get_masked(x, y), expected.get_masked(x, y));
+                assert_eq!(grid.get_interpolate(x, y), expected.get_interpolate(x, y));
+            }
+            assert!(matches!(
+                grid.get_index(header.nrows, 0),
+                Err(error::Error::OutOfBounds(..))
+            ));
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_reader() {
+        use crate::mmap::MmapEsriASCIIReader;
+
+        for (path, layout) in [
+            ("test_data/test_llcorner.asc", DataLayout::Lines),
+            ("test_data/test_llcenter.asc", DataLayout::Lines),
+            ("test_data/test_wrapped.asc", DataLayout::Stream),
+            ("test_data/test_single_line.asc", DataLayout::Stream),
+        ] {
+            let mut expected: EsriASCIIReader<File, f64, f64> =
+                EsriASCIIReader::from_file(File::open(path).unwrap())
+                    .unwrap()
+                    .with_layout(layout);
+            let grid: MmapEsriASCIIReader<f64, f64> =
+                MmapEsriASCIIReader::from_file_with_layout(&File::open(path).unwrap(), layout)
+                    .unwrap();
+            assert_eq!(grid.header, expected.header);
+            assert_eq!(grid.layout(), layout);
+            assert_eq!(grid.load_all().unwrap(), expected.load_all().unwrap());
+            for row in 0..grid.header.nrows {
+                for col in 0..grid.header.ncols {
+                    assert_eq!(
+                        grid.get_index_masked(row, col).unwrap(),
+                        expected.get_index_masked(row, col).unwrap()
+                    );
+                }
+            }
+            for (x, y) in [(10., 10.), (75., 120.), (199., 299.), (-1., 0.)] {
+                assert_eq!(grid.get(x, y), expected.get(x, y));
+                assert_eq!(grid.get_interpolate(x, y), expected.get_interpolate(x, y));
+            }
+            assert!(matches!(
+                grid.get_index(0, grid.header.ncols),
+                Err(error::Error::OutOfBounds(..))
+            ));
+        }
+
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let grid: MmapEsriASCIIReader<f64, f64> = MmapEsriASCIIReader::from_file(&file).unwrap();
+        assert_eq!(grid.get_index(4, 0).unwrap(), 88.);
+        assert!(matches!(
+            grid.get_index(5, 0),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        assert!(grid.load_all().is_err());
+
+        // A wrapped file read as lines has rows of the wrong length
+        let file = File::open("test_data/test_wrapped.asc").unwrap();
+        let grid: MmapEsriASCIIReader<f64, f64> = MmapEsriASCIIReader::from_file(&file).unwrap();
+        assert!(matches!(
+            grid.get_index(0, 3),
+            Err(error::Error::MismatchColumnCount(4, 3))
+        ));
+        assert!(matches!(
+            grid.row(0),
+            Err(error::Error::MismatchColumnCount(4, 3))
+        ));
+
+        // A header claiming far more cells than the file holds is an error, rather than sizing buffers from it
+        let path = std::env::temp_dir().join(format!(
+            "esri_ascii_grid_mmap_huge_{}.asc",
+            std::process::id()
+        ));
+        let huge = usize::MAX / 2;
+        std::fs::write(
+            &path,
+            format!("ncols {huge}\nnrows {huge}\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3 4\n"),
+        )
+        .unwrap();
+        for layout in [DataLayout::Lines, DataLayout::Stream] {
+            let grid: MmapEsriASCIIReader<f64, f64> =
+                MmapEsriASCIIReader::from_file_with_layout(&File::open(&path).unwrap(), layout)
+                    .unwrap();
+            assert!(grid.load_all().is_err());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_load_all_parallel() {
+        for (path, layout) in [
+            ("test_data/test_llcorner.asc", DataLayout::Lines),
+            ("test_data/test_no_nodata.asc", DataLayout::Lines),
+            ("test_data/test_wrapped.asc", DataLayout::Stream),
+            ("test_data/test_single_line.asc", DataLayout::Stream),
+        ] {
+            let open = || -> EsriASCIIReader<File, f64, f64> {
+                EsriASCIIReader::from_file(File::open(path).unwrap())
+                    .unwrap()
+                    .with_layout(layout)
+            };
+            let expected = open().load_all().unwrap();
+            let mut grid = open();
+            assert_eq!(grid.load_all_parallel().unwrap(), expected);
+            // The row offsets found while loading are used for random access
+            let offsets = open().row_offsets().unwrap();
+            assert_eq!(grid.row_offsets().unwrap(), offsets);
+            assert_eq!(
+                grid.get_index(5, 3).unwrap(),
+                expected.get_index(5, 3).unwrap()
+            );
+        }
+
+        // Large enough to be scanned in several chunks
+        let header: EsriASCIIRasterHeader<f64, i32> = EsriASCIIRasterHeader::new(
+            300,
+            500,
+            0.,
+            0.,
+            crate::header::CornerType::Corner,
+            1.,
+            None,
+        );
+        let rows = (0..500)
+            .map(|row| (0..300).map(|col| row * 1000 + col).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let data = EsriASCIIWriter::new(Vec::new(), header)
+            .write_grid(&rows)
+            .unwrap();
+        assert!(data.len() > 1 << 17);
+        let mut grid: EsriASCIIReader<_, f64, i32> =
+            EsriASCIIReader::from_file(Cursor::new(data)).unwrap();
+        let raster = grid.load_all_parallel().unwrap();
+        assert_eq!(raster.data(), rows.concat());
+
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert!(matches!(
+            grid.load_all_parallel(),
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        let file = File::open("test_data/test_wrapped.asc").unwrap();
+        let mut grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+        assert!(matches!(
+            grid.load_all_parallel(),
+            Err(error::Error::MismatchColumnCount(4, 3))
+        ));
+    }
+
+    #[cfg(feature = "fast-parse")]
+    #[test]
+    fn test_fast_parse() {
+        use crate::ascii_file::parse_value;
+
+        fn check<U>(tokens: &[&str])
+        where
+            U: Numerical + std::str::FromStr,
+            error::Error: From<<U as Numerical>::Err>,
+        {
+            for token in tokens {
+                let expected = token.parse::<U>().ok();
+                let parsed = parse_value::<U>(token, 0, 0).ok();
+                // Compare the text, so that every NaN and both zeros are told apart
+                assert_eq!(
+                    parsed.map(|value| format!("{value:?}")),
+                    expected.map(|value| format!("{value:?}")),
+                    "{token:?} as {}",
+                    std::any::type_name::<U>()
+                );
+            }
+        }
+        let tokens = [
+            "1",
+            "+1",
+            "-1",
+            "01",
+            "-0",
+            "1.",
+            ".5",
+            "-.5",
+            "+.5e3",
+            "1e5",
+            "1E5",
+            "1.5E+3",
+            "1.5e-3",
+            "1e",
+            "e5",
+            ".",
+            "-",
+            "+",
+            "",
+            "inf",
+            "-INF",
+            "infinity",
+            "NaN",
+            "nan",
+            "1_000",
+            "0x10",
+            " 1",
+            "1..2",
+            "--1",
+            "1e+",
+            "300",
+            "-129",
+            "3.4e39",
+            "1e400",
+            "1e-400",
+            "4e-324",
+            "-3.4028234663852886e38",
+            "141.2700042724609375",
+            "9999999999999999999999",
+        ];
+        check::<f64>(&tokens);
+        check::<f32>(&tokens);
+        check::<i8>(&tokens);
+        check::<i32>(&tokens);
+        check::<i64>(&tokens);
+        check::<u8>(&tokens);
+        check::<u64>(&tokens);
+        check::<usize>(&tokens);
+        let awkward = AWKWARD_VALUES.split_whitespace().collect::<Vec<_>>();
+        check::<f64>(&awkward);
+        check::<f32>(&awkward);
+    }
+
+    #[test]
+    fn test_stream_reader() {
+        for (path, layout) in [
+            ("test_data/test_llcorner.asc", DataLayout::Lines),
+            ("test_data/test_llcenter.asc", DataLayout::Lines),
+            ("test_data/test_wrapped.asc", DataLayout::Stream),
+            ("test_data/test_no_nodata_short.asc", DataLayout::Lines),
+        ] {
+            let expected = EsriASCIIReader::<File, f64, f64>::from_file(File::open(path).unwrap())
+                .unwrap()
+                .with_layout(layout)
+                .into_iter()
+                .map(|cell| cell.map_err(|err| err.to_string()))
+                .collect::<Vec<_>>();
+            let grid: EsriASCIIStreamReader<_, f64, f64> =
+                EsriASCIIStreamReader::new(BufReader::new(File::open(path).unwrap()))
+                    .unwrap()
+                    .with_layout(layout);
+            assert_eq!(grid.layout(), layout);
+            let cells = grid
+                .into_iter()
+                .map(|cell| cell.map_err(|err| err.to_string()))
+                .collect::<Vec<_>>();
+            assert_eq!(cells, expected);
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        let open = |path| -> EsriASCIIReader<File, f64, f64> {
+            EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap()
+        };
+        let (_, expected) = read_rows(open("test_data/test_llcorner.asc"));
+
+        let mut grid = open("test_data/test_llcorner.asc");
+        let rows = grid.rows().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 6);
+        for (row, values) in &rows {
+            assert_eq!(values, &expected[*row]);
+        }
+        // The rows were cached, so reading them again is free
+        assert_eq!(grid.cache_stats().misses, 6);
+        let mut rows = grid.rows();
+        let mut count = 0;
+        while let Some(row) = rows.next_row() {
+            let (row, values) = row.unwrap();
+            assert_eq!(values, expected[row]);
+            count += 1;
+        }
+        assert_eq!(count, 6);
+        assert!(rows.next_row().is_none());
+        assert_eq!(grid.get_index(3, 1).unwrap(), 42.);
+        assert_eq!(grid.cache_stats().misses, 6);
+        assert_eq!(grid.cache_stats().hits, 7);
+
+        // Consuming the reader moves cached rows out rather than reading them again
+        let mut grid =
+            open("test_data/test_llcorner.asc").with_cache_policy(CachePolicy::MaxRows(2));
+        grid.get_index(4, 0).unwrap();
+        let rows = grid
+            .into_rows()
+            .map(|row| row.unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, expected);
+
+        // Both stop at the first error
+        let mut grid = open("test_data/test_no_nodata_short.asc");
+        let rows = grid.rows().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 6);
+        assert!(matches!(
+            rows[5],
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        let rows = grid.into_rows().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 6);
+        assert!(rows[..5].iter().all(Result::is_ok));
+        let mut rows = open("test_data/test_wrapped.asc").into_rows();
+        assert!(matches!(
+            rows.next(),
+            Some(Err(error::Error::MismatchColumnCount(4, 3)))
+        ));
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_iter_region() {
+        let open = |path| -> EsriASCIIReader<File, f64, f64> {
+            EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap()
+        };
+        let (_, expected) = read_rows(open("test_data/test_llcorner.asc"));
+
+        let mut grid = open("test_data/test_llcorner.asc");
+        let mut count = 0;
+        for cell in grid.iter() {
+            let (row, col, value) = cell.unwrap();
+            assert_eq!(value, expected[row][col]);
+            count += 1;
+        }
+        assert_eq!(count, 24);
+        // Every row was read once, and is now cached
+        assert_eq!(grid.cache_stats().misses, 6);
+        assert_eq!(grid.get_index(4, 1).unwrap(), 75.);
+        assert_eq!(grid.cache_stats().misses, 6);
+
+        let mut grid = open("test_data/test_llcorner.asc");
+        let cells = grid
+            .iter_region(2..=3, 1..3)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(cells, [(2, 1, 8.), (2, 2, 35.), (3, 1, 42.), (3, 2, 50.)]);
+        assert_eq!(grid.iter_region(5.., 3..).count(), 1);
+        assert_eq!(grid.iter_region(2..2, ..).count(), 0);
+        let mut cells = grid.iter_region(.., 2..5);
+        assert!(matches!(
+            cells.next(),
+            Some(Err(error::Error::OutOfBounds(6, 5)))
+        ));
+        assert!(cells.next().is_none());
+    }
+
+    #[test]
+    fn test_read_window() {
+        let open = |path| -> EsriASCIIReader<File, f64, f64> {
+            EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap()
+        };
+        let mut grid = open("test_data/test_llcorner.asc");
+        // Edges that fall on cell boundaries do not pull in the neighbouring cells
+        let window = grid.read_bbox(50., 100., 150., 200.).unwrap();
+        assert_eq!(window.data(), [8., 35., 42., 50.]);
+        assert_eq!(window.header.num_rows(), 2);
+        assert_eq!(window.header.num_cols(), 2);
+        assert_eq!((window.header.min_x(), window.header.min_y()), (50., 100.));
+        assert_eq!((window.header.max_x(), window.header.max_y()), (150., 200.));
+        assert_eq!(window.get(60., 110.), Some(42.));
+        assert_eq!(window, grid.read_window(2..4, 1..=2).unwrap());
+
+        // Extents are clipped to the grid
+        let all = grid.read_bbox(-100., -100., 1000., 1000.).unwrap();
+        assert_eq!(all, grid.load_all().unwrap());
+        let line = grid.read_bbox(50., 100., 50., 200.).unwrap();
+        assert_eq!(line.data(), [8., 42.]);
+
+        assert!(matches!(
+            grid.read_bbox(300., 0., 400., 10.),
+            Err(error::Error::ExtentOutOfBounds(x, y)) if x == (300.0..400.0) && y == (0.0..10.0)
+        ));
+        // Extents that only touch an edge of the grid do not overlap it
+        let header = grid.header;
+        assert_eq!(header.window_of(-100., 0., 0., 300.), None);
+        assert_eq!(header.window_of(200., 0., 300., 300.), None);
+        assert_eq!(header.window_of(0., 300., 200., 400.), None);
+        assert_eq!(header.window_of(0., -100., 200., 0.), None);
+        // An extent with no width or height is in the cells to its right or above
+        assert_eq!(header.window_of(0., 0., 0., 300.), Some((0..6, 0..1)));
+        assert_eq!(header.window_of(10., 150., 30., 150.), Some((2..3, 0..1)));
+        assert!(matches!(
+            grid.read_window(0..7, ..),
+            Err(error::Error::OutOfBounds(7, 4))
+        ));
+        assert!(matches!(
+            grid.read_window(0..=usize::MAX, ..),
+            Err(error::Error::OutOfBounds(usize::MAX, 4))
+        ));
+        let mut cells = grid.iter_region(.., 0..=usize::MAX);
+        assert!(matches!(
+            cells.next(),
+            Some(Err(error::Error::OutOfBounds(6, usize::MAX)))
+        ));
+        assert!(matches!(
+            grid.read_window(2..2, ..),
+            Err(error::Error::EmptyWindow(rows, cols)) if rows == (2..2) && cols == (0..4)
+        ));
+
+        let mut grid = open("test_data/test_llcenter.asc");
+        let window = grid.read_bbox(0., 0., 100., 100.).unwrap();
+        assert_eq!(
+            window.header.corner_type(),
+            crate::header::CornerType::Center
+        );
+        assert_eq!((window.header.min_x(), window.header.min_y()), (-25., -25.));
+        assert_eq!(window.data(), [32., 42., 50., 88., 75., 27., 13., 5., 1.]);
+        // The window is written with the same corner type as its source
+        let text = String::from_utf8(window.write_to(Vec::new()).unwrap()).unwrap();
+        assert!(text.contains("xllcenter     0\n"));
+    }
+
+    #[test]
+    fn test_clip() {
+        let open = |path| -> EsriASCIIReader<File, f64, f64> {
+            EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap()
+        };
+        let reopen = |bytes: Vec<u8>| -> EsriASCIIReader<Cursor<Vec<u8>>, f64, f64> {
+            EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap()
+        };
+        let (_, expected) = read_rows(open("test_data/test_llcorner.asc"));
+        let mut grid = open("test_data/test_llcorner.asc");
+
+        let clipped = grid.clip_to(Vec::new(), 50., 100., 150., 200.).unwrap();
+        let window = grid.read_bbox(50., 100., 150., 200.).unwrap();
+        assert_eq!(reopen(clipped.clone()).load_all().unwrap(), window);
+        // Padding an extent inside the grid changes nothing
+        let padded = grid
+            .clip_to_padded(Vec::new(), 50., 100., 150., 200.)
+            .unwrap();
+        assert_eq!(padded, clipped);
+        // Clipping does not fill the cache
+        assert_eq!(grid.cache_stats().rows, 2);
+
+        let padded = grid
+            .clip_to_padded(Vec::new(), -60., 90., 120., 320.)
+            .unwrap();
+        let padded = reopen(padded).load_all().unwrap();
+        assert_eq!(padded.header.num_cols(), 5);
+        assert_eq!(padded.header.num_rows(), 6);
+        assert_eq!((padded.header.min_x(), padded.header.min_y()), (-100., 50.));
+        assert_eq!((padded.header.max_x(), padded.header.max_y()), (150., 350.));
+        assert_eq!(padded.row(0).unwrap(), [-9999.; 5]);
+        for (row, values) in expected.iter().take(5).enumerate() {
+            assert_eq!(padded.row(row + 1).unwrap()[..2], [-9999.; 2]);
+            assert_eq!(padded.row(row + 1).unwrap()[2..], values[..3]);
+        }
+        let outside = grid
+            .clip_to_padded(Vec::new(), 1000., 1000., 1010., 1010.)
+            .unwrap();
+        assert_eq!(reopen(outside).load_all().unwrap().data(), [-9999.]);
+
+        assert!(matches!(
+            grid.clip_to(Vec::new(), 1000., 1000., 1010., 1010.),
+            Err(error::Error::ExtentOutOfBounds(..))
+        ));
+        assert!(matches!(
+            grid.clip_to_padded(Vec::new(), 10., 0., 0., 10.),
+            Err(error::Error::InvertedExtent(x, y)) if x == (10.0..0.0) && y == (0.0..10.0)
+        ));
+        for (min_x, max_x) in [(f64::NAN, 10.), (0., f64::INFINITY), (-1e300, 1e300)] {
+            assert!(matches!(
+                grid.clip_to_padded(Vec::new(), min_x, 0., max_x, 10.),
+                Err(error::Error::InvalidExtent(..))
+            ));
+        }
+        // Within the grid, the same cells are chosen as by clip_to, including for extents on the edges of cells
+        for (min_x, min_y, max_x, max_y) in [
+            (0., 0., 200., 300.),
+            (50., 100., 50., 200.),
+            (10., 150., 30., 150.),
+            (0., 0., 0., 0.),
+            (120., 160., 200., 240.),
+        ] {
+            assert_eq!(
+                grid.clip_to_padded(Vec::new(), min_x, min_y, max_x, max_y)
+                    .unwrap(),
+                grid.clip_to(Vec::new(), min_x, min_y, max_x, max_y)
+                    .unwrap()
+            );
+        }
+        // Cells that only touch the extent are left out, even when they are in the grid
+        let touching = grid
+            .clip_to_padded(Vec::new(), -100., 0., 0., 300.)
+            .unwrap();
+        let touching = reopen(touching).load_all().unwrap();
+        assert_eq!(touching.header.num_cols(), 2);
+        assert!(touching.data().iter().all(|&value| value == -9999.));
+        // Padding needs a nodata value
+        let mut grid = open("test_data/test_no_nodata.asc");
+        assert!(grid.clip_to_padded(Vec::new(), 0., 0., 100., 100.).is_ok());
+        assert!(matches!(
+            grid.clip_to_padded(Vec::new(), -10., 0., 100., 100.),
+            Err(error::Error::PaddingWithoutNodata(rows, cols)) if rows == (4..6) && cols == (-1..2)
+        ));
+    }
+
+    #[test]
+    fn test_stream_rows() {
+        // Chain does not implement Seek, and splits the header across two reads
+        let source = std::fs::read("test_data/test_llcorner.asc").unwrap();
+        let (head, tail) = source.split_at(20);
+        let grid: EsriASCIIStreamReader<_, f64, f64> =
+            EsriASCIIStreamReader::from_reader(head.chain(tail)).unwrap();
+        assert_eq!(grid.header.nrows, 6);
+        let rows = grid.into_rows().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[1], (1, vec![-9999., 20., 100., 36.]));
+        assert_eq!(rows[5], (5, vec![13., 5., 1., -9999.]));
+
+        // Cells continue from the rows already read
+        let mut grid: EsriASCIIStreamReader<_, f64, f64> =
+            EsriASCIIStreamReader::from_reader(source.as_slice()).unwrap();
+        assert_eq!(grid.next_row().unwrap().unwrap().0, 0);
+        let cells = grid.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(cells.len(), 5 * 4);
+        assert_eq!(cells[0], (1, 0, -9999.));
+        assert_eq!(cells[19], (5, 3, -9999.));
+
+        // Rows stop after the first error
+        let file = File::open("test_data/test_no_nodata_short.asc").unwrap();
+        let rows = EsriASCIIStreamReader::<_, f64, f64>::from_reader(file)
+            .unwrap()
+            .into_rows()
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 6);
+        assert!(matches!(
+            rows[5],
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        let file = File::open("test_data/test_wrapped.asc").unwrap();
+        let mut grid = EsriASCIIStreamReader::<_, f64, f64>::from_reader(file).unwrap();
+        assert!(matches!(
+            grid.next_row(),
+            Some(Err(error::Error::MismatchColumnCount(4, 3)))
+        ));
+        assert!(grid.next_row().is_none());
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn test_compressed() {
+        use crate::compression::Compression;
+
+        let dir =
+            std::env::temp_dir().join(format!("esri_ascii_grid_compressed_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = std::fs::read("test_data/test_llcorner.asc").unwrap();
+        let mut files = vec![(dir.join("grid.asc"), source.clone())];
+        #[cfg(feature = "gzip")]
+        {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&source).unwrap();
+            files.push((dir.join("grid.asc.gz"), encoder.finish().unwrap()));
+        }
+        #[cfg(feature = "zstd")]
+        files.push((
+            dir.join("grid.asc.zst"),
+            zstd::encode_all(source.as_slice(), 0).unwrap(),
+        ));
+
+        let file = File::open("test_data/test_llcorner.asc").unwrap();
+        let mut expected: EsriASCIIReader<File, f64, f64> =
+            EsriASCIIReader::from_file(file).unwrap();
+        let expected = expected.load_all().unwrap();
+        for (path, data) in &files {
+            std::fs::write(path, data).unwrap();
+            let mut grid: EsriASCIIReader<_, f64, f64> =
+                EsriASCIIReader::from_compressed_file(path).unwrap();
+            assert_eq!(grid.header, expected.header);
+            assert_eq!(grid.get_index(5, 2).unwrap(), 1.);
+            assert_eq!(grid.load_all().unwrap(), expected);
+
+            let grid: EsriASCIIStreamReader<_, f64, f64> =
+                EsriASCIIStreamReader::from_compressed_file(path).unwrap();
+            let values = grid
+                .into_iter()
+                .map(|cell| cell.unwrap().2)
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected.data());
+        }
+        assert_eq!(Compression::detect(&source), Compression::None);
+        #[cfg(feature = "gzip")]
+        assert_eq!(Compression::detect(&files[1].1), Compression::Gzip);
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            Compression::detect(&files.last().unwrap().1),
+            Compression::Zstd
+        );
+
+        // A truncated stream is an error rather than a short grid
+        let (path, data) = files.last().unwrap();
+        if data != &source {
+            std::fs::write(path, &data[..data.len() / 2]).unwrap();
+            assert!(EsriASCIIReader::<_, f64, f64>::from_compressed_file(path).is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_reader() {
+        use crate::async_reader::{AsyncEsriASCIIReader, AsyncEsriASCIIStreamReader};
+        use futures_util::StreamExt;
+
+        for (path, layout) in [
+            ("test_data/test_llcorner.asc", DataLayout::Lines),
+            ("test_data/test_llcenter.asc", DataLayout::Lines),
+            ("test_data/test_wrapped.asc", DataLayout::Stream),
+            ("test_data/test_single_line.asc", DataLayout::Stream),
+        ] {
+            let mut expected: EsriASCIIReader<File, f64, f64> =
+                EsriASCIIReader::from_file(File::open(path).unwrap())
+                    .unwrap()
+                    .with_layout(layout);
+            let expected = expected.load_all().unwrap();
+            let data = std::fs::read(path).unwrap();
+
+            let grid: AsyncEsriASCIIStreamReader<_, f64, f64> =
+                AsyncEsriASCIIStreamReader::new(data.as_slice())
+                    .await
+                    .unwrap()
+                    .with_layout(layout);
+            assert_eq!(grid.header, expected.header);
+            assert_eq!(grid.layout(), layout);
+            let rows = grid
+                .into_rows()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(rows.concat(), expected.data());
+
+            let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+                AsyncEsriASCIIReader::from_file(Cursor::new(data))
+                    .await
+                    .unwrap()
+                    .with_layout(layout)
+                    .with_cache_policy(crate::cache::CachePolicy::MaxRows(1));
+            assert_eq!(grid.layout(), layout);
+            let header = grid.header;
+            // Bottom up, so that every row is found before it is read
+            for row in (0..header.nrows).rev() {
+                for col in 0..header.ncols {
+                    assert_eq!(
+                        grid.get_index_masked(row, col).await.unwrap(),
+                        expected.get_index_masked(row, col).unwrap()
+                    );
+                }
+            }
+            assert_eq!(grid.cache_stats().misses, header.nrows as u64);
+            for (x, y) in [(10., 10.), (75., 120.), (199., 299.), (-1., 0.)] {
+                assert_eq!(grid.get(x, y).await, expected.get(x, y));
+                assert_eq!(grid.get_masked(x, y).await, expected.get_masked(x, y));
+            }
+            assert!(matches!(
+                grid.get_index(header.nrows, 0).await,
+                Err(error::Error::OutOfBounds(..))
+            ));
+        }
+
+        // A row that fails to parse leaves the reader partway through it, which must not shift later reads
+        let data = "ncols 2\nnrows 3\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 abc\n5 6\n7 8\n";
+        let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+            AsyncEsriASCIIReader::from_file(Cursor::new(data))
+                .await
+                .unwrap();
+        for _ in 0..2 {
+            assert!(matches!(
+                grid.get_index(0, 0).await,
+                Err(error::Error::TypeCast(..))
+            ));
+        }
+        assert_eq!(grid.get_index(1, 0).await.unwrap(), 5.);
+        assert_eq!(grid.get_index(2, 0).await.unwrap(), 7.);
+
+        let data = std::fs::read("test_data/test_no_nodata_short.asc").unwrap();
+        let grid: AsyncEsriASCIIStreamReader<_, f64, f64> =
+            AsyncEsriASCIIStreamReader::new(data.as_slice())
+                .await
+                .unwrap();
+        let rows = grid.into_rows().collect::<Vec<_>>().await;
+        assert_eq!(rows.len(), 6);
+        assert!(matches!(
+            rows[5],
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+            AsyncEsriASCIIReader::from_file(Cursor::new(data))
+                .await
+                .unwrap();
+        assert!(matches!(
+            grid.get_index(5, 0).await,
+            Err(error::Error::MismatchedRowCount(6, 5))
+        ));
+        assert_eq!(grid.get_index(4, 0).await.unwrap(), 88.);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_interpolate() {
+        use crate::async_reader::AsyncEsriASCIIReader;
+
+        for path in ["test_data/test_llcorner.asc", "test_data/test_llcenter.asc"] {
+            let mut expected: EsriASCIIReader<File, f64, f64> =
+                EsriASCIIReader::from_file(File::open(path).unwrap()).unwrap();
+            let expected = expected.load_all().unwrap();
+            let mut grid: AsyncEsriASCIIReader<_, f64, f64> =
+                AsyncEsriASCIIReader::from_file(Cursor::new(std::fs::read(path).unwrap()))
+                    .await
+                    .unwrap();
+            let header = grid.header;
+            assert_eq!(grid.cell_registration(), header.cell_registration());
+            // Interpolation follows the registration given by the header, or set on the reader
+            let points = [
+                (10., 10.),
+                (75., 120.),
+                (110., 190.),
+                (199., 299.),
+                (-1., 0.),
+            ];
+            for registration in [
+                header.cell_registration(),
+                CellRegistration::PixelIsArea,
+                CellRegistration::PixelIsPoint,
+            ] {
+                grid = grid.with_cell_registration(registration);
+                assert_eq!(grid.cell_registration(), registration);
+                let expected = expected.clone().with_cell_registration(registration);
+                for (x, y) in points {
+                    assert_eq!(
+                        grid.get_interpolate(x, y).await,
+                        expected.get_interpolate(x, y)
+                    );
+                    for policy in [NodataPolicy::Skip, NodataPolicy::Refuse] {
+                        assert_eq!(
+                            grid.get_interpolate_with(x, y, policy).await.unwrap(),
+                            expected.get_interpolate_with(x, y, policy)
+                        );
+                    }
+                    for method in [
+                        Interpolation::Nearest,
+                        Interpolation::Bilinear,
+                        Interpolation::Bicubic,
+                    ] {
+                        assert_eq!(
+                            grid.sample(x, y, method).await.unwrap(),
+                            expected.sample(x, y, method)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     #[cfg(feature = "ordered-float")]
     #[test]
     fn can_parse_into_notnan() {
@@ -0,0 +1,291 @@
+use std::{fs::File, io};
+
+use memchr::memchr_iter;
+use memmap2::Mmap;
+
+use crate::{
+    ascii_file::{DataLayout, parse_row, parse_value, stream_row_starts},
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+    interpolate::{self, CellRegistration, Interpolation, NodataPolicy},
+    raster::EsriASCIIRaster,
+};
+
+/// A reader for ESRI ASCII raster files that memory-maps the file.
+///
+/// The start of every row is found when the reader is created, by scanning the mapped file for line breaks.
+/// Values are then parsed straight from the mapped bytes, so reading a cell needs no seeking, buffering or copying,
+/// and every accessor takes `&self`. No rows are cached, as the operating system keeps recently used pages in memory.
+///
+/// Only available with the `mmap` feature.
+///
+/// # Type Parameters
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct MmapEsriASCIIReader<T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    mmap: Mmap,
+    /// The byte offset of the start of every row found in the file, which may be fewer than `nrows` if the file is short.
+    row_starts: Vec<usize>,
+    layout: DataLayout,
+    registration: CellRegistration,
+}
+impl<T, U> MmapEsriASCIIReader<T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Memory-maps a file and finds the start of every row, expecting every row on its own line.
+    ///
+    /// The file must not be changed by this or any other process while the reader exists, as the mapped bytes would change underneath it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::mmap::MmapEsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: MmapEsriASCIIReader<f64, f64> = MmapEsriASCIIReader::from_file(&file).unwrap();
+    /// assert_eq!(grid.get_index(0, 2).unwrap(), 5.0);
+    /// assert_eq!(grid.get(175.0, 25.0), Some(-9999.0));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be mapped, or there is something wrong with the header.
+    pub fn from_file(file: &File) -> Result<Self, Error> {
+        Self::from_file_with_layout(file, DataLayout::Lines)
+    }
+    /// Memory-maps a file and finds the start of every row, with the values laid out as given by `layout`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::{ascii_file::DataLayout, mmap::MmapEsriASCIIReader};
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_wrapped.asc").unwrap();
+    /// let grid: MmapEsriASCIIReader<f64, f64> =
+    ///     MmapEsriASCIIReader::from_file_with_layout(&file, DataLayout::Stream).unwrap();
+    /// assert_eq!(grid.get_index(0, 3).unwrap(), 2.0);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be mapped, or there is something wrong with the header.
+    pub fn from_file_with_layout(file: &File, layout: DataLayout) -> Result<Self, Error> {
+        // SAFETY: the mapping is only ever read, and callers are told not to modify the file while it is mapped
+        let mmap = unsafe { Mmap::map(file)? };
+        let mut rest = &mmap[..];
+        let header = EsriASCIIRasterHeader::from_buf_read(&mut rest)?;
+        let data_start = mmap.len() - rest.len();
+        let row_starts = find_row_starts(&mmap, data_start, layout, header.nrows, header.ncols);
+        Ok(Self {
+            header,
+            mmap,
+            row_starts,
+            layout,
+            registration: header.cell_registration(),
+        })
+    }
+    /// Sets how interpolation treats the edges of the grid, overriding the registration given by the header.
+    ///
+    /// Behaves the same as `EsriASCIIReader::with_cell_registration`.
+    #[must_use]
+    pub fn with_cell_registration(mut self, registration: CellRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+    /// Returns how interpolation treats the edges of the grid.
+    pub fn cell_registration(&self) -> CellRegistration {
+        self.registration
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Returns the value at the given row and column.
+    /// 0, 0 is the top left corner. The row and column are zero indexed.
+    ///
+    /// Only the requested value is parsed; the rest of the row is just checked to have the right number of values.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds, the row is missing or has the wrong number of values,
+    /// or the value is not a valid number.
+    pub fn get_index(&self, row: usize, col: usize) -> Result<U, Error> {
+        if row >= self.header.nrows || col >= self.header.ncols {
+            Err(Error::OutOfBounds(row, col))?;
+        }
+        let mut tokens = self.row_text(row)?.split_ascii_whitespace();
+        let token = tokens.nth(col);
+        let len = col + usize::from(token.is_some()) + tokens.count();
+        let complete = match self.layout {
+            DataLayout::Lines => len == self.header.ncols,
+            // The last row may be followed by more values, which are ignored as when reading sequentially
+            DataLayout::Stream => len >= self.header.ncols,
+        };
+        match token {
+            Some(token) if complete => parse_value(token, row, col),
+            _ => Err(Error::MismatchColumnCount(self.header.ncols, len)),
+        }
+    }
+    /// Returns the values of the given row.
+    ///
+    /// # Errors
+    /// Returns an error if the row is out of bounds or missing, has the wrong number of values, or has a value that is not a valid number.
+    pub fn row(&self, row: usize) -> Result<Vec<U>, Error> {
+        if row >= self.header.nrows {
+            Err(Error::OutOfBounds(row, 0))?;
+        }
+        let text = self.row_text(row)?;
+        let ncols = self.header.ncols;
+        match self.layout {
+            DataLayout::Lines => parse_row(text, row, ncols),
+            DataLayout::Stream => {
+                let values = text
+                    .split_ascii_whitespace()
+                    .take(ncols)
+                    .enumerate()
+                    .map(|(col, token)| parse_value(token, row, col))
+                    .collect::<Result<Vec<U>, Error>>()?;
+                if values.len() != ncols {
+                    Err(Error::MismatchColumnCount(ncols, values.len()))?;
+                }
+                Ok(values)
+            }
+        }
+    }
+    /// Reads every cell of the grid into an owned, in-memory `EsriASCIIRaster`.
+    ///
+    /// # Errors
+    /// Returns an error if any row is missing, has the wrong number of values, or has a value that is not a valid number.
+    pub fn load_all(&self) -> Result<EsriASCIIRaster<T, U>, Error> {
+        // Every value takes at least two bytes of the file, so a header claiming more cells than that cannot be trusted to size the buffer
+        let cells = self.header.nrows.checked_mul(self.header.ncols);
+        let mut data =
+            Vec::with_capacity(cells.map_or(0, |cells| cells.min(self.mmap.len() / 2 + 1)));
+        for row in 0..self.header.nrows {
+            data.extend(self.row(row)?);
+        }
+        Ok(EsriASCIIRaster::new(self.header, data)?.with_cell_registration(self.registration))
+    }
+    /// Returns the value at the given row and column, or nothing if the cell is nodata.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    pub fn get_index_masked(&self, row: usize, col: usize) -> Result<Option<U>, Error> {
+        let value = self.get_index(row, col)?;
+        Ok(self.header.mask(value))
+    }
+    /// Returns the value of the cell containing the given x and y coordinates, or nothing if they are out of bounds.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get`.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub fn get(&self, x: T, y: T) -> Option<U> {
+        let (row, col) = self.header.index_of(x, y)?;
+        Some(self.get_index(row, col).unwrap())
+    }
+    /// Returns the value at the given x and y coordinates, or nothing if they are out of bounds or the cell is nodata.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub fn get_masked(&self, x: T, y: T) -> Option<U> {
+        let value = self.get(x, y)?;
+        self.header.mask(value)
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate`.
+    ///
+    /// # Panics
+    /// Panics if a contributing cell cannot be read. Use `get_interpolate_with` to handle the error instead.
+    pub fn get_interpolate(&self, x: T, y: T) -> Option<U> {
+        self.get_interpolate_with(x, y, NodataPolicy::default())
+            .unwrap()
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells,
+    /// handling nodata cells according to `policy`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate_with`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn get_interpolate_with(
+        &self,
+        x: T,
+        y: T,
+        policy: NodataPolicy,
+    ) -> Result<Option<U>, Error> {
+        interpolate::bilinear(&self.header, self.registration, x, y, policy, |row, col| {
+            self.get_index(row, col)
+        })
+    }
+    /// Returns the value at the given x and y coordinates, sampled from the nearby cells using `method`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::sample`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn sample(&self, x: T, y: T, method: Interpolation) -> Result<Option<U>, Error> {
+        interpolate::sample(
+            &self.header,
+            self.registration,
+            x,
+            y,
+            method,
+            NodataPolicy::default(),
+            |row, col| self.get_index(row, col),
+        )
+    }
+    /// Returns the text of the given row. For `DataLayout::Stream` this runs up to the start of the next row.
+    fn row_text(&self, row: usize) -> Result<&str, Error> {
+        let start = match self.row_starts.get(row) {
+            Some(&start) if start < self.mmap.len() => start,
+            _ => Err(Error::MismatchedRowCount(self.header.nrows, row))?,
+        };
+        let end = match self.layout {
+            DataLayout::Lines => memchr::memchr(b'\n', &self.mmap[start..])
+                .map_or(self.mmap.len(), |len| start + len),
+            DataLayout::Stream => self
+                .row_starts
+                .get(row + 1)
+                .copied()
+                .unwrap_or(self.mmap.len()),
+        };
+        std::str::from_utf8(&self.mmap[start..end])
+            .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err)))
+    }
+}
+
+/// Finds the byte offset of the start of up to `nrows` rows, beginning at `data_start`.
+fn find_row_starts(
+    data: &[u8],
+    data_start: usize,
+    layout: DataLayout,
+    nrows: usize,
+    ncols: usize,
+) -> Vec<usize> {
+    // Every row takes at least one byte of the file, which bounds how many rows there can be whatever the header says
+    let mut row_starts = Vec::with_capacity(nrows.min(data.len() - data_start + 1));
+    if nrows == 0 {
+        return row_starts;
+    }
+    match layout {
+        DataLayout::Lines => {
+            row_starts.push(data_start);
+            row_starts.extend(
+                memchr_iter(b'\n', &data[data_start..])
+                    .map(|newline| data_start + newline + 1)
+                    .take(nrows - 1),
+            );
+        }
+        DataLayout::Stream => {
+            row_starts.extend(
+                stream_row_starts(&data[data_start..], nrows, ncols)
+                    .into_iter()
+                    .map(|start| data_start + start),
+            );
+        }
+    }
+    row_starts
+}
@@ -0,0 +1,103 @@
+use std::io;
+
+use rayon::prelude::*;
+
+use crate::{
+    ascii_file::{DataLayout, parse_value, stream_row_starts},
+    error::{self, Error},
+    header::Numerical,
+};
+
+/// The number of bytes each task scans for line breaks.
+const SCAN_CHUNK: usize = 1 << 16;
+
+/// Parses the data section of a grid in parallel into a contiguous buffer in row-major order.
+///
+/// Rows are found first, by scanning fixed size byte ranges for line breaks in parallel, and then the rows are parsed
+/// in parallel, each straight into its place in the buffer. Also returns the byte offset of the start of every row.
+pub(crate) fn parse_grid<U>(
+    data: &[u8],
+    layout: DataLayout,
+    nrows: usize,
+    ncols: usize,
+) -> Result<(Vec<U>, Vec<usize>), Error>
+where
+    U: Numerical + Send + Sync,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    let mut row_starts = match layout {
+        DataLayout::Lines => line_starts(data, nrows),
+        DataLayout::Stream => stream_row_starts(data, nrows, ncols),
+    };
+    // A line break at the very end of the data does not start another row
+    row_starts.retain(|&start| start < data.len());
+    if row_starts.len() < nrows {
+        Err(Error::MismatchedRowCount(nrows, row_starts.len()))?;
+    }
+    let mut values = vec![U::zero(); nrows * ncols];
+    if ncols > 0 {
+        values
+            .par_chunks_mut(ncols)
+            .enumerate()
+            .try_for_each(|(row, out)| {
+                let end = match layout {
+                    DataLayout::Lines => data[row_starts[row]..]
+                        .iter()
+                        .position(|&byte| byte == b'\n')
+                        .map_or(data.len(), |len| row_starts[row] + len),
+                    DataLayout::Stream => row_starts.get(row + 1).copied().unwrap_or(data.len()),
+                };
+                parse_row_into(&data[row_starts[row]..end], layout, row, out)
+            })?;
+    }
+    Ok((values, row_starts))
+}
+
+/// Returns the start of the first `nrows` lines.
+fn line_starts(data: &[u8], nrows: usize) -> Vec<usize> {
+    if nrows == 0 {
+        return Vec::new();
+    }
+    let mut starts = vec![0];
+    starts.par_extend(
+        data.par_chunks(SCAN_CHUNK)
+            .enumerate()
+            .flat_map_iter(|(chunk, bytes)| {
+                bytes
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &byte)| byte == b'\n')
+                    .map(move |(offset, _)| chunk * SCAN_CHUNK + offset + 1)
+            }),
+    );
+    starts.truncate(nrows);
+    starts
+}
+
+/// Parses the text of one row into `out`, which holds exactly `ncols` values.
+fn parse_row_into<U>(
+    text: &[u8],
+    layout: DataLayout,
+    row: usize,
+    out: &mut [U],
+) -> Result<(), Error>
+where
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    let text = std::str::from_utf8(text)
+        .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+    let mut tokens = text.split_ascii_whitespace();
+    let mut len = 0;
+    for (value, token) in out.iter_mut().zip(tokens.by_ref()) {
+        *value = parse_value(token, row, len)?;
+        len += 1;
+    }
+    if layout == DataLayout::Lines {
+        len += tokens.count();
+    }
+    if len != out.len() {
+        Err(Error::MismatchColumnCount(out.len(), len))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::{fmt::Display, io::Write, iter::StepBy, slice::ChunksExact};
+
+use crate::{
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+    interpolate::{self, CellRegistration, Interpolation, NodataPolicy},
+    writer::EsriASCIIWriter,
+};
+
+/// An ESRI ASCII raster held entirely in memory.
+///
+/// Unlike `EsriASCIIReader`, every accessor takes `&self`, so a raster can be shared between threads.
+/// The values are stored contiguously in row-major order, with row 0 being the top row.
+///
+/// # Type Parameters
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug, Clone, PartialEq)]
+pub struct EsriASCIIRaster<T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    data: Vec<U>,
+    registration: CellRegistration,
+}
+impl<T, U> EsriASCIIRaster<T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `EsriASCIIRaster` from a header and the values of every cell in row-major order.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::header::{CornerType, EsriASCIIRasterHeader};
+    /// use esri_ascii_grid::raster::EsriASCIIRaster;
+    /// let header: EsriASCIIRasterHeader<f64, i32> =
+    ///     EsriASCIIRasterHeader::new(3, 2, 0.0, 0.0, CornerType::Corner, 10.0, Some(-9999));
+    /// let raster = EsriASCIIRaster::new(header, vec![1, 2, 3, 4, 5, 6]).unwrap();
+    /// assert_eq!(raster.get_index(1, 0).unwrap(), 4);
+    /// assert_eq!(raster.get(25.0, 15.0), Some(3));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the header has no columns, or if `data` does not contain exactly `ncols * nrows` values.
+    pub fn new(header: EsriASCIIRasterHeader<T, U>, data: Vec<U>) -> Result<Self, Error> {
+        if header.num_cols() == 0 {
+            Err(Error::BrokenInvariant(
+                "a raster must have at least one column".into(),
+            ))?;
+        }
+        let expected = header.num_rows() * header.num_cols();
+        if data.len() != expected {
+            Err(Error::MismatchedCellCount(expected, data.len()))?;
+        }
+        Ok(Self {
+            header,
+            data,
+            registration: header.cell_registration(),
+        })
+    }
+    /// Sets how interpolation treats the edges of the grid, overriding the registration given by the header.
+    ///
+    /// Behaves the same as `EsriASCIIReader::with_cell_registration`.
+    #[must_use]
+    pub fn with_cell_registration(mut self, registration: CellRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+    /// Returns how interpolation treats the edges of the grid.
+    pub fn cell_registration(&self) -> CellRegistration {
+        self.registration
+    }
+    /// Returns the values of every cell in row-major order.
+    pub fn data(&self) -> &[U] {
+        &self.data
+    }
+    /// Consumes the raster, returning the values of every cell in row-major order.
+    pub fn into_data(self) -> Vec<U> {
+        self.data
+    }
+    /// Returns the value at the given row and column.
+    /// 0, 0 is the top left corner. The row and column are zero indexed.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds.
+    pub fn get_index(&self, row: usize, col: usize) -> Result<U, Error> {
+        self.offset(row, col).map(|offset| self.data[offset])
+    }
+    /// Sets the value at the given row and column.
+    /// 0, 0 is the top left corner. The row and column are zero indexed.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds.
+    pub fn set_index(&mut self, row: usize, col: usize, value: U) -> Result<(), Error> {
+        let offset = self.offset(row, col)?;
+        self.data[offset] = value;
+        Ok(())
+    }
+    /// Returns the value of the cell containing the given x and y coordinates, or nothing if they are out of bounds.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get`.
+    pub fn get(&self, x: T, y: T) -> Option<U> {
+        let (row, col) = self.header.index_of(x, y)?;
+        self.get_index(row, col).ok()
+    }
+    /// Returns the value at the given row and column, or nothing if the cell is nodata.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds.
+    pub fn get_index_masked(&self, row: usize, col: usize) -> Result<Option<U>, Error> {
+        self.get_index(row, col)
+            .map(|value| self.header.mask(value))
+    }
+    /// Returns the value of the cell containing the given x and y coordinates, or nothing if they are out of bounds or the cell is nodata.
+    pub fn get_masked(&self, x: T, y: T) -> Option<U> {
+        self.get(x, y).and_then(|value| self.header.mask(value))
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate`.
+    pub fn get_interpolate(&self, x: T, y: T) -> Option<U> {
+        self.get_interpolate_with(x, y, NodataPolicy::default())
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells,
+    /// handling nodata cells according to `policy`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate_with`.
+    pub fn get_interpolate_with(&self, x: T, y: T, policy: NodataPolicy) -> Option<U> {
+        // Every cell is already in memory, so reading one cannot fail
+        interpolate::bilinear(&self.header, self.registration, x, y, policy, |row, col| {
+            self.get_index(row, col)
+        })
+        .ok()
+        .flatten()
+    }
+    /// Returns the value at the given x and y coordinates, sampled from the nearby cells using `method`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::sample`.
+    pub fn sample(&self, x: T, y: T, method: Interpolation) -> Option<U> {
+        interpolate::sample(
+            &self.header,
+            self.registration,
+            x,
+            y,
+            method,
+            NodataPolicy::default(),
+            |row, col| self.get_index(row, col),
+        )
+        .ok()
+        .flatten()
+    }
+    /// Returns the values of the given row, or nothing if it is out of bounds.
+    pub fn row(&self, row: usize) -> Option<&[U]> {
+        let ncols = self.header.num_cols();
+        self.data.get(row * ncols..(row + 1) * ncols)
+    }
+    /// Returns the values of the given row mutably, or nothing if it is out of bounds.
+    pub fn row_mut(&mut self, row: usize) -> Option<&mut [U]> {
+        let ncols = self.header.num_cols();
+        self.data.get_mut(row * ncols..(row + 1) * ncols)
+    }
+    /// Returns an iterator over every row, from top to bottom.
+    pub fn rows(&self) -> ChunksExact<'_, U> {
+        self.data.chunks_exact(self.header.num_cols())
+    }
+    /// Returns an iterator over the values of the given column from top to bottom, or nothing if it is out of bounds.
+    pub fn column(&self, col: usize) -> Option<StepBy<std::slice::Iter<'_, U>>> {
+        if col >= self.header.num_cols() {
+            return None;
+        }
+        Some(self.data[col..].iter().step_by(self.header.num_cols()))
+    }
+    /// Writes the raster as an ESRI ASCII grid to `writer`, and returns the writer.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying writer fails.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<W, Error>
+    where
+        T: Display,
+        U: Display,
+    {
+        EsriASCIIWriter::new(writer, self.header).write_grid(self.rows())
+    }
+    fn offset(&self, row: usize, col: usize) -> Result<usize, Error> {
+        if row >= self.header.num_rows() || col >= self.header.num_cols() {
+            Err(Error::OutOfBounds(row, col))?;
+        }
+        Ok(row * self.header.num_cols() + col)
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    ascii_file::{DataLayout, EsriASCIIReader, LineSeeker, parse_row, read_next_row, seek_to_line},
+    cache::{CacheStats, RowCache},
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+    interpolate::{self, CellRegistration, Interpolation, NodataPolicy},
+};
+
+/// A reader for ESRI ASCII raster files that can be shared between threads.
+///
+/// Every accessor takes `&self`. Rows are read with positional reads on a single file handle, so threads never
+/// fight over a shared file cursor, and decoded rows are kept in a row cache shared by every thread.
+/// On platforms other than Unix and Windows, which have no positional reads, threads take turns to move the file cursor.
+///
+/// Create one with `SharedEsriASCIIReader::from_file`, or from a configured reader with `EsriASCIIReader::into_shared`.
+///
+/// # Type Parameters
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct SharedEsriASCIIReader<T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    file: SharedFile,
+    layout: DataLayout,
+    row_starts: Mutex<RowStarts>,
+    line_cache: Mutex<RowCache<U>>,
+    registration: CellRegistration,
+}
+/// The byte offsets of the rows found so far.
+#[derive(Debug)]
+struct RowStarts {
+    line_start_cache: Vec<Option<u64>>,
+    line_seeker: LineSeeker,
+}
+impl<T, U> SharedEsriASCIIReader<T, U>
+where
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `SharedEsriASCIIReader` from a file, reading only the header at first.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::shared::SharedEsriASCIIReader;
+    /// use std::fs::File;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: SharedEsriASCIIReader<f64, f64> = SharedEsriASCIIReader::from_file(file).unwrap();
+    /// std::thread::scope(|scope| {
+    ///     let top = scope.spawn(|| grid.get_index(0, 2).unwrap());
+    ///     let bottom = scope.spawn(|| grid.get_index(5, 2).unwrap());
+    ///     assert_eq!(top.join().unwrap(), 5.0);
+    ///     assert_eq!(bottom.join().unwrap(), 1.0);
+    /// });
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is something wrong with the header, such as missing values.
+    pub fn from_file(file: File) -> Result<Self, Error> {
+        Ok(EsriASCIIReader::from_file(file)?.into_shared())
+    }
+    pub(crate) fn from_parts(
+        header: EsriASCIIRasterHeader<T, U>,
+        file: File,
+        layout: DataLayout,
+        line_start_cache: Vec<Option<u64>>,
+        line_seeker: LineSeeker,
+        line_cache: RowCache<U>,
+    ) -> Self {
+        Self {
+            header,
+            file: SharedFile::new(file),
+            layout,
+            row_starts: Mutex::new(RowStarts {
+                line_start_cache,
+                line_seeker,
+            }),
+            line_cache: Mutex::new(line_cache),
+            registration: header.cell_registration(),
+        }
+    }
+    /// Sets how interpolation treats the edges of the grid, overriding the registration given by the header.
+    ///
+    /// Behaves the same as `EsriASCIIReader::with_cell_registration`.
+    #[must_use]
+    pub fn with_cell_registration(mut self, registration: CellRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+    /// Returns how interpolation treats the edges of the grid.
+    pub fn cell_registration(&self) -> CellRegistration {
+        self.registration
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Returns the hit, miss and eviction counts of the row cache, along with its current size.
+    pub fn cache_stats(&self) -> CacheStats {
+        lock(&self.line_cache).stats()
+    }
+    /// Returns the value at the given row and column.
+    /// 0, 0 is the top left corner. The row and column are zero indexed.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_index`.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    ///
+    /// # Panics
+    /// Panics if a cached row is missing right after being found in the cache, which should not happen as the cache is locked in between.
+    pub fn get_index(&self, row: usize, col: usize) -> Result<U, Error> {
+        if row >= self.header.nrows || col >= self.header.ncols {
+            Err(Error::OutOfBounds(row, col))?;
+        }
+        {
+            let mut line_cache = lock(&self.line_cache);
+            if line_cache.touch(row) {
+                return Ok(line_cache.get(row).unwrap()[col]);
+            }
+        }
+        // The cache is not locked while reading, so other threads can use it in the meantime
+        let values = self.read_row(row)?;
+        let value = values[col];
+        lock(&self.line_cache).insert(row, values);
+        Ok(value)
+    }
+    /// Returns the value at the given row and column, or nothing if the cell is nodata.
+    ///
+    /// # Errors
+    /// Returns an error if the row or column is out of bounds or is not a valid number.
+    pub fn get_index_masked(&self, row: usize, col: usize) -> Result<Option<U>, Error> {
+        let value = self.get_index(row, col)?;
+        Ok(self.header.mask(value))
+    }
+    /// Returns the value of the cell containing the given x and y coordinates, or nothing if they are out of bounds.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get`.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub fn get(&self, x: T, y: T) -> Option<U> {
+        let (row, col) = self.header.index_of(x, y)?;
+        Some(self.get_index(row, col).unwrap())
+    }
+    /// Returns the value at the given x and y coordinates, or nothing if they are out of bounds or the cell is nodata.
+    ///
+    /// # Panics
+    /// Panics if the cell cannot be read.
+    pub fn get_masked(&self, x: T, y: T) -> Option<U> {
+        let value = self.get(x, y)?;
+        self.header.mask(value)
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate`.
+    ///
+    /// # Panics
+    /// Panics if a contributing cell cannot be read. Use `get_interpolate_with` to handle the error instead.
+    pub fn get_interpolate(&self, x: T, y: T) -> Option<U> {
+        self.get_interpolate_with(x, y, NodataPolicy::default())
+            .unwrap()
+    }
+    /// Returns the value at the given x and y coordinates, interpolated from the four nearest cells,
+    /// handling nodata cells according to `policy`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::get_interpolate_with`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn get_interpolate_with(
+        &self,
+        x: T,
+        y: T,
+        policy: NodataPolicy,
+    ) -> Result<Option<U>, Error> {
+        interpolate::bilinear(&self.header, self.registration, x, y, policy, |row, col| {
+            self.get_index(row, col)
+        })
+    }
+    /// Returns the value at the given x and y coordinates, sampled from the nearby cells using `method`.
+    ///
+    /// Behaves the same as `EsriASCIIReader::sample`.
+    ///
+    /// # Errors
+    /// Returns an error if a contributing cell cannot be read.
+    pub fn sample(&self, x: T, y: T, method: Interpolation) -> Result<Option<U>, Error> {
+        interpolate::sample(
+            &self.header,
+            self.registration,
+            x,
+            y,
+            method,
+            NodataPolicy::default(),
+            |row, col| self.get_index(row, col),
+        )
+    }
+    /// Reads and parses the given row from the file, bypassing the cache.
+    ///
+    /// Once the start of the next row is known, the row is read in one go, without buffering past its end.
+    fn read_row(&self, row: usize) -> Result<Vec<U>, Error> {
+        let span = {
+            let line_start_cache = &lock(&self.row_starts).line_start_cache;
+            line_start_cache[row].zip(line_start_cache.get(row + 1).copied().flatten())
+        };
+        if let Some((start, end)) = span {
+            return self.read_row_between(row, start, end);
+        }
+        let mut reader = BufReader::new(PositionalReader {
+            file: &self.file,
+            position: 0,
+        });
+        let known = lock(&self.row_starts).line_start_cache[row];
+        if let Some(line_pos) = known {
+            reader.seek(SeekFrom::Start(line_pos))?;
+        } else {
+            // Scan while holding the lock, so that threads never scan the same part of the file twice
+            let mut row_starts = lock(&self.row_starts);
+            let RowStarts {
+                line_start_cache,
+                line_seeker,
+            } = &mut *row_starts;
+            if let Some(line_pos) = line_start_cache[row] {
+                reader.seek(SeekFrom::Start(line_pos))?;
+            } else {
+                seek_to_line(
+                    &mut reader,
+                    row,
+                    self.layout,
+                    self.header.ncols,
+                    line_seeker,
+                    line_start_cache,
+                )?;
+            }
+        }
+        let mut line = String::new();
+        let values = read_next_row(&mut reader, self.layout, row, self.header.ncols, &mut line)?
+            .ok_or(Error::MismatchedRowCount(self.header.nrows, row))?;
+        // Remember where the next row starts, so reading the grid in order never scans a row twice
+        let next_row = row + 1;
+        if next_row < self.header.nrows {
+            let position = reader.stream_position()?;
+            let mut row_starts = lock(&self.row_starts);
+            if row_starts.line_start_cache[next_row].is_none() {
+                row_starts.line_start_cache[next_row] = Some(position);
+                if row_starts.line_seeker.line < next_row {
+                    row_starts.line_seeker.update(next_row, position);
+                }
+            }
+        }
+        Ok(values)
+    }
+    /// Reads and parses the given row, which starts at `start` and ends where the next row starts at `end`, in a single read.
+    fn read_row_between(&self, row: usize, start: u64, end: u64) -> Result<Vec<U>, Error> {
+        let len = end
+            .checked_sub(start)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(|| Error::BrokenInvariant(format!("row {row} ends before it starts")))?;
+        let mut text = vec![0; len];
+        PositionalReader {
+            file: &self.file,
+            position: start,
+        }
+        .read_exact(&mut text)?;
+        let line = std::str::from_utf8(&text)
+            .map_err(|err| Error::Io(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+        parse_row(line, row, self.header.ncols)
+    }
+}
+
+/// Locks a mutex, carrying on if another thread panicked while holding it, as the cached state is always valid.
+fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A file that many threads can read at any position at once.
+#[derive(Debug)]
+struct SharedFile {
+    file: File,
+    /// Held while seeking and reading, on platforms where every read has to move the file's cursor.
+    #[cfg(not(any(unix, windows)))]
+    cursor: Mutex<()>,
+}
+impl SharedFile {
+    fn new(file: File) -> Self {
+        Self {
+            file,
+            #[cfg(not(any(unix, windows)))]
+            cursor: Mutex::new(()),
+        }
+    }
+    /// Reads from the given position in the file.
+    ///
+    /// On Unix, this never moves the file's cursor. On Windows it does, but every read gives its own position, so nothing
+    /// relies on where the cursor is.
+    fn read_at(&self, buf: &mut [u8], position: u64) -> io::Result<usize> {
+        #[cfg(unix)]
+        return std::os::unix::fs::FileExt::read_at(&self.file, buf, position);
+        #[cfg(windows)]
+        return std::os::windows::fs::FileExt::seek_read(&self.file, buf, position);
+        #[cfg(not(any(unix, windows)))]
+        {
+            // Seek and read under the lock, so another thread cannot move the cursor in between
+            let _cursor = lock(&self.cursor);
+            let mut file = &self.file;
+            file.seek(SeekFrom::Start(position))?;
+            file.read(buf)
+        }
+    }
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+}
+
+/// Reads a shared file at its own position, without moving the file's cursor, so many can read the same file at once.
+struct PositionalReader<'a> {
+    file: &'a SharedFile,
+    position: u64,
+}
+impl Read for PositionalReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.file.read_at(buf, self.position)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+impl Seek for PositionalReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(position);
+            }
+            SeekFrom::End(offset) => (self.file.len()?, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        self.position = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
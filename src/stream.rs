@@ -0,0 +1,222 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    vec::IntoIter,
+};
+
+use crate::{
+    ascii_file::{DataLayout, read_next_row},
+    error::{self, Error},
+    header::{EsriASCIIRasterHeader, Numerical},
+};
+
+/// A reader for ESRI ASCII raster files that only reads forward, so it does not need the source to implement `Seek`.
+///
+/// The header is read when the reader is created, and the grid is then read once, in order, either row by row or by
+/// iterating over every cell. This suits sources that cannot seek, such as stdin, pipes, network streams and decompressors.
+///
+/// # Type Parameters
+/// * `B` - The type of the source. This should implement `BufRead`.
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct EsriASCIIStreamReader<B, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    reader: B,
+    layout: DataLayout,
+    line: String,
+    row: usize,
+    terminated: bool,
+}
+impl<R, T, U> EsriASCIIStreamReader<BufReader<R>, T, U>
+where
+    R: Read,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `EsriASCIIStreamReader` from any source that implements `Read`, buffering it.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use esri_ascii_grid::stream::EsriASCIIStreamReader;
+    /// // Sum every value of a grid piped to the program
+    /// let grid: EsriASCIIStreamReader<_, f64, f64> = EsriASCIIStreamReader::from_reader(std::io::stdin()).unwrap();
+    /// let header = grid.header;
+    /// let sum: f64 = grid
+    ///     .into_iter()
+    ///     .filter_map(|cell| header.mask(cell.unwrap().2))
+    ///     .sum();
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is something wrong with the header, such as missing values.
+    pub fn from_reader(reader: R) -> Result<Self, Error> {
+        Self::new(BufReader::new(reader))
+    }
+}
+impl<B, T, U> EsriASCIIStreamReader<B, T, U>
+where
+    B: BufRead,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `EsriASCIIStreamReader`, reading the header from the current position of `reader`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::stream::EsriASCIIStreamReader;
+    /// let data = "ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3 4\n";
+    /// let grid: EsriASCIIStreamReader<_, f64, f64> = EsriASCIIStreamReader::new(data.as_bytes()).unwrap();
+    /// let values = grid.into_iter().map(|cell| cell.unwrap().2).collect::<Vec<_>>();
+    /// assert_eq!(values, [1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if there is something wrong with the header, such as missing values.
+    pub fn new(mut reader: B) -> Result<Self, Error> {
+        let header = EsriASCIIRasterHeader::from_buf_read(&mut reader)?;
+        Ok(Self {
+            header,
+            reader,
+            layout: DataLayout::Lines,
+            line: String::new(),
+            row: 0,
+            terminated: false,
+        })
+    }
+    /// Sets how the values of the grid are laid out across the lines of the file.
+    #[must_use]
+    pub fn with_layout(mut self, layout: DataLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+    /// Returns how the values of the grid are laid out across the lines of the file.
+    pub fn layout(&self) -> DataLayout {
+        self.layout
+    }
+    /// Reads the next row, returning its index and values.
+    ///
+    /// Returns nothing once every row has been read, or after an error has been returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::stream::EsriASCIIStreamReader;
+    /// let data = "ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3 4\n";
+    /// let mut grid: EsriASCIIStreamReader<_, f64, f64> = EsriASCIIStreamReader::new(data.as_bytes()).unwrap();
+    /// assert_eq!(grid.next_row().unwrap().unwrap(), (0, vec![1.0, 2.0]));
+    /// assert_eq!(grid.next_row().unwrap().unwrap(), (1, vec![3.0, 4.0]));
+    /// assert!(grid.next_row().is_none());
+    /// ```
+    pub fn next_row(&mut self) -> Option<Result<(usize, Vec<U>), Error>> {
+        if self.terminated || self.row >= self.header.nrows {
+            self.terminated = true;
+            return None;
+        }
+        let row = self.row;
+        let values = read_next_row(
+            &mut self.reader,
+            self.layout,
+            row,
+            self.header.ncols,
+            &mut self.line,
+        )
+        .and_then(|values| values.ok_or(Error::MismatchedRowCount(self.header.nrows, row)));
+        match values {
+            Ok(values) => {
+                self.row += 1;
+                Some(Ok((row, values)))
+            }
+            Err(error) => {
+                self.terminated = true;
+                Some(Err(error))
+            }
+        }
+    }
+    /// Turns this reader into an iterator over the remaining rows, yielding the index and values of each row.
+    ///
+    /// If an error is encountered at any point, the iterator will return an `Err` once and halt.
+    pub fn into_rows(self) -> EsriASCIIStreamRows<B, T, U> {
+        EsriASCIIStreamRows { reader: self }
+    }
+}
+impl<B, T, U> IntoIterator for EsriASCIIStreamReader<B, T, U>
+where
+    B: BufRead,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, usize, U), Error>;
+    type IntoIter = EsriASCIIStreamIntoIterator<B, T, U>;
+    /// Returns an iterator over the values of the remaining rows, behaving the same as the iterator of `EsriASCIIReader`.
+    fn into_iter(self) -> Self::IntoIter {
+        EsriASCIIStreamIntoIterator {
+            header: self.header,
+            reader: self,
+            row_it: None,
+            col: 0,
+        }
+    }
+}
+
+/// An iterator over the rows of a raster read by an `EsriASCIIStreamReader`. Created by `EsriASCIIStreamReader::into_rows`.
+#[derive(Debug)]
+pub struct EsriASCIIStreamRows<B, T: Numerical, U: Numerical> {
+    reader: EsriASCIIStreamReader<B, T, U>,
+}
+impl<B, T, U> Iterator for EsriASCIIStreamRows<B, T, U>
+where
+    B: BufRead,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, Vec<U>), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.next_row()
+    }
+}
+
+/// An iterator over the cells of a raster read by an `EsriASCIIStreamReader`, from left to right, top to bottom.
+///
+/// If an error is encountered at any point, the iterator will return an `Err` once and halt.
+#[derive(Debug)]
+pub struct EsriASCIIStreamIntoIterator<B, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    reader: EsriASCIIStreamReader<B, T, U>,
+    row_it: Option<(usize, IntoIter<U>)>,
+    col: usize,
+}
+impl<B, T, U> Iterator for EsriASCIIStreamIntoIterator<B, T, U>
+where
+    B: BufRead,
+    T: Numerical,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    type Item = Result<(usize, usize, U), Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((row, values)) = &mut self.row_it
+                && let Some(value) = values.next()
+            {
+                let col = self.col;
+                self.col += 1;
+                return Some(Ok((*row, col, value)));
+            }
+            match self.reader.next_row()? {
+                Ok((row, values)) => {
+                    self.row_it = Some((row, values.into_iter()));
+                    self.col = 0;
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::io;
+
+use crate::{ascii_file::DataLayout, error::Error};
+
+/// Finds the text of the next row of a grid, one buffer at a time, without reading anything itself.
+///
+/// The sync and async readers drive it in the same way: pass the reader's buffer to `feed`, consume as many bytes as it
+/// took, and repeat until the row is complete. An empty buffer marks the end of the data.
+#[derive(Debug)]
+pub(crate) struct RowTokenizer {
+    layout: DataLayout,
+    ncols: usize,
+    /// The text of the row so far. In the stream layout, its values are joined by single spaces.
+    bytes: Vec<u8>,
+    /// The number of values found so far, which is only counted in the stream layout.
+    values: usize,
+    in_value: bool,
+    consumed: u64,
+    complete: bool,
+}
+impl RowTokenizer {
+    /// Starts a new row, reusing the allocation of `scratch` for its text.
+    pub(crate) fn new(layout: DataLayout, ncols: usize, scratch: &mut String) -> Self {
+        let mut bytes = std::mem::take(scratch).into_bytes();
+        bytes.clear();
+        Self {
+            layout,
+            ncols,
+            bytes,
+            values: 0,
+            in_value: false,
+            consumed: 0,
+            complete: false,
+        }
+    }
+    /// Returns true once the row is complete, and nothing more should be fed to it.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.complete
+    }
+    /// Returns the number of bytes taken so far.
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn consumed(&self) -> u64 {
+        self.consumed
+    }
+    /// Takes the bytes at the start of `buf` that belong to the row, returning how many were taken.
+    ///
+    /// A row laid out as `DataLayout::Lines` ends after its line break. A row laid out as `DataLayout::Stream` ends
+    /// just after its last value, so the whitespace that follows is left for the next row, as with reading the values
+    /// one at a time.
+    pub(crate) fn feed(&mut self, buf: &[u8]) -> usize {
+        if buf.is_empty() {
+            self.complete = true;
+            return 0;
+        }
+        let taken = match self.layout {
+            DataLayout::Lines => {
+                let taken = match buf.iter().position(|&byte| byte == b'\n') {
+                    Some(end) => {
+                        self.complete = true;
+                        end + 1
+                    }
+                    None => buf.len(),
+                };
+                self.bytes.extend_from_slice(&buf[..taken]);
+                taken
+            }
+            DataLayout::Stream => self.feed_values(buf),
+        };
+        self.consumed += taken as u64;
+        taken
+    }
+    /// Takes whitespace separated values from `buf` until there are `ncols` of them, regardless of line breaks.
+    fn feed_values(&mut self, buf: &[u8]) -> usize {
+        let mut offset = 0;
+        while offset < buf.len() {
+            let rest = &buf[offset..];
+            if self.in_value {
+                let len = rest
+                    .iter()
+                    .position(u8::is_ascii_whitespace)
+                    .unwrap_or(rest.len());
+                self.bytes.extend_from_slice(&rest[..len]);
+                offset += len;
+                if offset == buf.len() {
+                    // The value may carry on into the next buffer
+                    break;
+                }
+                self.in_value = false;
+                if self.values == self.ncols {
+                    self.complete = true;
+                    return offset;
+                }
+            } else {
+                offset += rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+                if offset < buf.len() {
+                    if self.values > 0 {
+                        self.bytes.push(b' ');
+                    }
+                    self.values += 1;
+                    self.in_value = true;
+                }
+            }
+        }
+        buf.len()
+    }
+    /// Moves the text of the complete row into `line`. Returns false if the data ended before the row started.
+    ///
+    /// # Errors
+    /// Returns an error if the row is not valid UTF-8, or if the data ends partway through a row laid out as `DataLayout::Stream`.
+    pub(crate) fn finish(self, line: &mut String) -> Result<bool, Error> {
+        debug_assert!(self.complete);
+        *line = String::from_utf8(self.bytes).map_err(|err| {
+            Error::Io(io::Error::new(io::ErrorKind::InvalidData, err.utf8_error()))
+        })?;
+        match self.layout {
+            DataLayout::Lines => Ok(self.consumed > 0),
+            DataLayout::Stream => match self.values {
+                0 => Ok(false),
+                values if values < self.ncols => {
+                    Err(Error::MismatchColumnCount(self.ncols, values))
+                }
+                _ => Ok(true),
+            },
+        }
+    }
+}
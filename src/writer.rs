@@ -0,0 +1,311 @@
+use std::{
+    fmt::Display,
+    io::{BufWriter, Write},
+};
+
+use num_traits::NumCast;
+
+use crate::{
+    error::{self, Error},
+    header::{CornerType, EsriASCIIRasterHeader, Numerical, is_same_value},
+};
+
+/// Width that header keys are padded to, matching the files in `test_data`.
+const KEY_WIDTH: usize = 14;
+
+/// How grid values are formatted when they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// The shortest representation that parses back to exactly the same value.
+    #[default]
+    Shortest,
+    /// A fixed number of digits after the decimal point, e.g. `Fixed(2)` writes `1.50`.
+    Fixed(usize),
+    /// A number of significant digits. Very large or small values switch to scientific notation, like `%g` in C.
+    ///
+    /// Intended for float grids, as integer types cannot parse scientific notation.
+    Significant(usize),
+    /// Scientific notation with a fixed number of digits after the decimal point, e.g. `Scientific(2)` writes `1.50e2`.
+    ///
+    /// Intended for float grids, as integer types cannot parse scientific notation.
+    Scientific(usize),
+}
+
+/// Options controlling how an `EsriASCIIWriter` formats the grid.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// The format of every value that is not nodata.
+    pub format: NumberFormat,
+    /// The token written for nodata cells and in the `NODATA_value` header line.
+    ///
+    /// If this is `None`, the shortest representation of the header's nodata value is used.
+    pub nodata_token: Option<String>,
+}
+
+/// A writer for ESRI ASCII raster files.
+/// The header is written first, followed by the rows of the grid from top to bottom.
+/// Rows are streamed to the output one at a time, so the whole grid never needs to be held in memory.
+///
+/// # Type Parameters
+/// * `W` - The type of the output. This should be something that implements `Write`.
+/// * `T` - The type of the coordinates. Should be a number.
+/// * `U` - The type of the height values in the grid. Should be a number
+#[derive(Debug)]
+pub struct EsriASCIIWriter<W: Write, T: Numerical, U: Numerical> {
+    pub header: EsriASCIIRasterHeader<T, U>,
+    writer: BufWriter<W>,
+    format: NumberFormat,
+    nodata_token: Option<String>,
+    header_written: bool,
+    rows_written: usize,
+}
+impl<W, T, U> EsriASCIIWriter<W, T, U>
+where
+    W: Write,
+    T: Numerical + Display,
+    error::Error: From<<T as Numerical>::Err>,
+    U: Numerical + Display,
+    error::Error: From<<U as Numerical>::Err>,
+{
+    /// Create a new `EsriASCIIWriter` that will write a grid described by `header` to `writer`.
+    ///
+    /// Nothing is written until the first row is written or the writer is finished.
+    pub fn new(writer: W, header: EsriASCIIRasterHeader<T, U>) -> Self {
+        Self::with_options(writer, header, WriteOptions::default())
+    }
+    /// Create a new `EsriASCIIWriter` that formats values according to `options`.
+    ///
+    /// Cells equal to the header's nodata value are always written as the nodata token,
+    /// so that they still match the `NODATA_value` line when read back.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::header::{CornerType, EsriASCIIRasterHeader};
+    /// use esri_ascii_grid::writer::{EsriASCIIWriter, NumberFormat, WriteOptions};
+    /// let header: EsriASCIIRasterHeader<f64, f64> =
+    ///     EsriASCIIRasterHeader::new(3, 1, 0.0, 0.0, CornerType::Corner, 10.0, Some(-3.4e38));
+    /// let options = WriteOptions {
+    ///     format: NumberFormat::Fixed(2),
+    ///     nodata_token: Some("-9999".to_string()),
+    /// };
+    /// let mut writer = EsriASCIIWriter::with_options(Vec::new(), header, options);
+    /// writer.write_row(&[1.5, -3.4e38, 2.0 / 3.0]).unwrap();
+    /// let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+    /// assert!(text.contains("NODATA_value  -9999\n"));
+    /// assert!(text.ends_with("1.50 -9999 0.67\n"));
+    /// ```
+    pub fn with_options(
+        writer: W,
+        header: EsriASCIIRasterHeader<T, U>,
+        options: WriteOptions,
+    ) -> Self {
+        let nodata_token = options
+            .nodata_token
+            .or_else(|| header.nodata_value.map(|value| value.to_string()));
+        Self {
+            header,
+            writer: BufWriter::new(writer),
+            format: options.format,
+            nodata_token,
+            header_written: false,
+            rows_written: 0,
+        }
+    }
+    /// Returns the number of rows written so far.
+    pub fn rows_written(&self) -> usize {
+        self.rows_written
+    }
+    /// Writes the next row of the grid. Row 0 is the top row.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::header::{CornerType, EsriASCIIRasterHeader};
+    /// use esri_ascii_grid::writer::EsriASCIIWriter;
+    /// let header: EsriASCIIRasterHeader<f64, i32> =
+    ///     EsriASCIIRasterHeader::new(3, 2, 0.0, 0.0, CornerType::Corner, 10.0, Some(-9999));
+    /// let mut writer = EsriASCIIWriter::new(Vec::new(), header);
+    /// writer.write_row(&[1, 2, 3]).unwrap();
+    /// // Rows must have exactly `ncols` values
+    /// assert!(writer.write_row(&[4, 5]).is_err());
+    /// writer.write_row(&[4, 5, 6]).unwrap();
+    /// let bytes = writer.finish().unwrap();
+    /// assert!(String::from_utf8(bytes).unwrap().ends_with("1 2 3\n4 5 6\n"));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the row does not have `header.ncols` values, if all `header.nrows` rows have already been written,
+    /// or if writing to the underlying writer fails.
+    pub fn write_row(&mut self, row: &[U]) -> Result<(), Error> {
+        if row.len() != self.header.ncols {
+            Err(Error::MismatchColumnCount(self.header.ncols, row.len()))?;
+        }
+        if self.rows_written >= self.header.nrows {
+            Err(Error::MismatchedRowCount(
+                self.header.nrows,
+                self.rows_written + 1,
+            ))?;
+        }
+        self.ensure_header()?;
+        let nodata = self.header.nodata_value.zip(self.nodata_token.as_deref());
+        write_row(&mut self.writer, row, self.format, nodata)?;
+        self.rows_written += 1;
+        Ok(())
+    }
+    /// Flushes the output and returns the underlying writer.
+    ///
+    /// # Errors
+    /// Returns an error if fewer than `header.nrows` rows have been written, or if writing to the underlying writer fails.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if self.rows_written != self.header.nrows {
+            Err(Error::MismatchedRowCount(
+                self.header.nrows,
+                self.rows_written,
+            ))?;
+        }
+        self.ensure_header()?;
+        self.writer
+            .into_inner()
+            .map_err(|err| Error::Io(err.into_error()))
+    }
+    /// Writes the header followed by every row of the grid, and returns the underlying writer.
+    ///
+    /// Row 0 is the top row. Each row should contain `header.ncols` values.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use esri_ascii_grid::ascii_file::EsriASCIIReader;
+    /// use esri_ascii_grid::writer::EsriASCIIWriter;
+    /// use std::fs::File;
+    /// use std::io::Cursor;
+    /// let file = File::open("test_data/test_llcorner.asc").unwrap();
+    /// let grid: EsriASCIIReader<File, f64, f64> = EsriASCIIReader::from_file(file).unwrap();
+    /// let header = grid.header;
+    /// let mut rows = vec![Vec::new(); header.num_rows()];
+    /// for cell in grid {
+    ///     let (row, _, value) = cell.unwrap();
+    ///     rows[row].push(value);
+    /// }
+    /// let writer = EsriASCIIWriter::new(Vec::new(), header);
+    /// let bytes = writer.write_grid(&rows).unwrap();
+    ///
+    /// let mut written: EsriASCIIReader<_, f64, f64> =
+    ///     EsriASCIIReader::from_file(Cursor::new(bytes)).unwrap();
+    /// assert_eq!(written.header, header);
+    /// assert_eq!(written.get_index(2, 2).unwrap(), 35.0);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if any row does not have `header.ncols` values, if there are not exactly `header.nrows` rows,
+    /// or if writing to the underlying writer fails.
+    pub fn write_grid<I, Row>(mut self, rows: I) -> Result<W, Error>
+    where
+        I: IntoIterator<Item = Row>,
+        Row: AsRef<[U]>,
+    {
+        for row in rows {
+            self.write_row(row.as_ref())?;
+        }
+        self.finish()
+    }
+    fn ensure_header(&mut self) -> Result<(), Error> {
+        if !self.header_written {
+            write_header(&mut self.writer, &self.header, self.nodata_token.as_deref())?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the header lines, restoring the original `xll`/`yll` for `CornerType::Center` grids.
+fn write_header<W, T, U>(
+    writer: &mut W,
+    header: &EsriASCIIRasterHeader<T, U>,
+    nodata_token: Option<&str>,
+) -> Result<(), Error>
+where
+    W: Write,
+    T: Numerical + Display,
+    U: Numerical + Display,
+{
+    let (xll_key, yll_key, xll, yll) = match header.cornertype {
+        CornerType::Corner => ("xllcorner", "yllcorner", header.xll, header.yll),
+        CornerType::Center => {
+            let two: T = NumCast::from(2).unwrap();
+            (
+                "xllcenter",
+                "yllcenter",
+                header.xll + header.dx / two,
+                header.yll + header.dy / two,
+            )
+        }
+    };
+    writeln!(writer, "{:<KEY_WIDTH$}{}", "ncols", header.ncols)?;
+    writeln!(writer, "{:<KEY_WIDTH$}{}", "nrows", header.nrows)?;
+    writeln!(writer, "{xll_key:<KEY_WIDTH$}{xll}")?;
+    writeln!(writer, "{yll_key:<KEY_WIDTH$}{yll}")?;
+    if header.dx == header.dy {
+        writeln!(writer, "{:<KEY_WIDTH$}{}", "cellsize", header.dx)?;
+    } else {
+        writeln!(writer, "{:<KEY_WIDTH$}{}", "dx", header.dx)?;
+        writeln!(writer, "{:<KEY_WIDTH$}{}", "dy", header.dy)?;
+    }
+    if let Some(nodata_token) = nodata_token {
+        writeln!(writer, "{:<KEY_WIDTH$}{nodata_token}", "NODATA_value")?;
+    }
+    Ok(())
+}
+
+fn write_row<W: Write, U: Numerical + Display>(
+    writer: &mut W,
+    row: &[U],
+    format: NumberFormat,
+    nodata: Option<(U, &str)>,
+) -> Result<(), Error> {
+    for (i, &value) in row.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b" ")?;
+        }
+        match nodata {
+            Some((nodata_value, token)) if is_same_value(value, nodata_value) => {
+                writer.write_all(token.as_bytes())?;
+            }
+            _ => write_value(writer, value, format)?,
+        }
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_value<W: Write, U: Numerical + Display>(
+    writer: &mut W,
+    value: U,
+    format: NumberFormat,
+) -> Result<(), Error> {
+    match format {
+        NumberFormat::Shortest => write!(writer, "{value}")?,
+        NumberFormat::Fixed(decimals) => write!(writer, "{value:.decimals$}")?,
+        NumberFormat::Scientific(decimals) => write!(writer, "{:.decimals$e}", as_f64(value))?,
+        NumberFormat::Significant(digits) => {
+            let digits = digits.max(1);
+            let value = as_f64(value);
+            let scientific = format!("{value:.prec$e}", prec = digits - 1);
+            let digits = i32::try_from(digits).unwrap_or(i32::MAX);
+            // The exponent after rounding decides between fixed and scientific notation
+            let exponent: i32 = scientific
+                .rsplit_once('e')
+                .and_then(|(_, exponent)| exponent.parse().ok())
+                .unwrap_or(0);
+            if exponent < -5 || exponent >= digits {
+                writer.write_all(scientific.as_bytes())?;
+            } else {
+                let decimals = (digits - 1 - exponent).max(0) as usize;
+                write!(writer, "{value:.decimals$}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn as_f64<U: Numerical>(value: U) -> f64 {
+    <f64 as NumCast>::from(value).unwrap_or(f64::NAN)
+}